@@ -0,0 +1,480 @@
+use crate::convert::{
+    json_to_assignment, json_to_assumptions, json_to_configuration_result, json_to_facts,
+    json_to_ground_request, json_to_symbol,
+};
+use crate::ground_program::GroundProgram;
+use crate::theory::TheoryRegistry;
+use crate::upload::Workspace;
+use crate::utils::{ModelResult, ServerError, Solver};
+use clingo::SolveMode;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+
+fn entry_default() -> bool {
+    true
+}
+
+/// Whether a path is a relative path without `..`
+fn is_inside(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// The text of a program in a command
+///
+/// The command log refers to the copies of the program files in its files directory,
+/// exported sessions have the texts inline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Program {
+    Text(String),
+    File(PathBuf),
+}
+impl Program {
+    /// The program with its file copied to `dir`, the copy is named by the sha256 of the text
+    ///
+    /// The file is copied without reading it into memory, an existing copy is kept.
+    pub fn stored(&self, dir: &Path) -> Result<Program, ServerError> {
+        match self {
+            Program::Text(_) => Ok(self.clone()),
+            Program::File(path) => {
+                let mut sha256 = Sha256::new();
+                io::copy(&mut File::open(path)?, &mut sha256)?;
+                let name = format!("{:x}", sha256.finalize());
+                let target = dir.join(&name);
+                if !target.exists() {
+                    // the copy gets its name once it is complete
+                    let file = NamedTempFile::new_in(dir)?;
+                    fs::copy(path, file.path())?;
+                    file.persist(&target).map_err(|e| e.error)?;
+                }
+                Ok(Program::File(PathBuf::from(name)))
+            }
+        }
+    }
+    /// The program with its file resolved against the files directory `dir` of a command log
+    pub fn resolved(&self, dir: &Path) -> Result<Program, ServerError> {
+        match self {
+            Program::Text(_) => Ok(self.clone()),
+            Program::File(name) if is_inside(name) => Ok(Program::File(dir.join(name))),
+            Program::File(name) => Err(ServerError::InternalError(format!(
+                "Invalid program file {:?}! Expected a file of the command log.",
+                name
+            ))),
+        }
+    }
+    /// The program with its text inline
+    pub fn inlined(&self) -> Result<Program, ServerError> {
+        match self {
+            Program::Text(_) => Ok(self.clone()),
+            Program::File(path) => Ok(Program::Text(fs::read_to_string(path)?)),
+        }
+    }
+    /// The program with its file linked to `target`, the file is copied if it can not be linked
+    ///
+    /// Files in the workspace are replaced and not overwritten, so the link keeps the added text.
+    fn linked(&self, target: PathBuf) -> Result<Program, ServerError> {
+        match self {
+            Program::Text(_) => Ok(self.clone()),
+            Program::File(path) => {
+                if fs::hard_link(path, &target).is_err() {
+                    fs::copy(path, &target)?;
+                }
+                Ok(Program::File(target))
+            }
+        }
+    }
+    /// Write the program to `path`, an existing file is replaced
+    fn write_to(&self, path: &Path) -> Result<(), ServerError> {
+        let file = NamedTempFile::new_in(path.parent().unwrap_or_else(|| Path::new(".")))?;
+        match self {
+            Program::Text(text) => fs::write(file.path(), text)?,
+            Program::File(source) => {
+                fs::copy(source, file.path())?;
+            }
+        }
+        file.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+/// A command of a session as written to the command log
+///
+/// Structured arguments are kept as the json data of the request,
+/// so that the replay parses them like the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    Create {
+        arguments: Vec<String>,
+    },
+    Add {
+        program: String,
+    },
+    /// A program of the library with its resolved reference like `queens@v3`
+    AddProgramRef {
+        reference: String,
+        program: Program,
+    },
+    /// A program file stored in the workspace under `name`
+    AddFile {
+        name: String,
+        program: Program,
+        /// Files only included by other files are stored in the workspace but not added
+        #[serde(default = "entry_default")]
+        entry: bool,
+    },
+    Facts {
+        facts: Value,
+    },
+    AddGroundProgram {
+        program: GroundProgram,
+    },
+    Aspif {
+        program: Program,
+    },
+    Ground {
+        request: Value,
+    },
+    AssignExternal {
+        assignment: Value,
+    },
+    ReleaseExternal {
+        symbol: Value,
+    },
+    RegisterTheory {
+        name: String,
+        options: Vec<(String, String)>,
+    },
+    SetConfigurationValue {
+        path: String,
+        value: String,
+    },
+    SetConfiguration {
+        configuration: Value,
+    },
+    Solve,
+    SolveWithAssumptions {
+        assumptions: Value,
+    },
+    Resume,
+    Close,
+}
+
+impl Command {
+    /// Whether the command is part of the state of a control object, solving is not
+    pub fn is_state(&self) -> bool {
+        !matches!(
+            self,
+            Command::Solve
+                | Command::SolveWithAssumptions { .. }
+                | Command::Resume
+                | Command::Close
+        )
+    }
+    /// The program of a command referring to a program file
+    pub fn program(&self) -> Option<&Program> {
+        match self {
+            Command::AddProgramRef { program, .. }
+            | Command::AddFile { program, .. }
+            | Command::Aspif { program } => Some(program),
+            _ => None,
+        }
+    }
+    /// The command with its program replaced by `f`
+    pub fn map_program(
+        &self,
+        f: impl FnOnce(&Program) -> Result<Program, ServerError>,
+    ) -> Result<Command, ServerError> {
+        let mut command = self.clone();
+        match &mut command {
+            Command::AddProgramRef { program, .. }
+            | Command::AddFile { program, .. }
+            | Command::Aspif { program } => *program = f(program)?,
+            _ => {}
+        }
+        Ok(command)
+    }
+}
+
+/// The commands building a session, they recreate an equivalent control object
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionBundle {
+    pub commands: Vec<Command>,
+}
+
+/// The directory of the program files of a command log, `session.jsonl` has `session.files`
+pub fn files_dir(path: &Path) -> PathBuf {
+    path.with_extension("files")
+}
+
+/// The commands of the current session in a temporary directory
+///
+/// The program files of the commands are linked into the directory,
+/// so the session is neither kept in memory nor are its files read before it is exported.
+struct SessionRecord {
+    dir: TempDir,
+    commands: File,
+    files: usize,
+}
+impl SessionRecord {
+    fn new() -> io::Result<SessionRecord> {
+        let dir = tempfile::Builder::new().prefix("cl-session-").tempdir()?;
+        let commands = File::create(dir.path().join("commands.jsonl"))?;
+        Ok(SessionRecord {
+            dir,
+            commands,
+            files: 0,
+        })
+    }
+    fn push(&mut self, command: &Command) -> Result<(), ServerError> {
+        let command = command.map_program(|program| {
+            self.files += 1;
+            program.linked(self.dir.path().join(self.files.to_string()))
+        })?;
+        writeln!(self.commands, "{}", serialize(&command)?)?;
+        Ok(())
+    }
+    /// The commands of the session starting with the command at `start`
+    fn commands(&self, start: usize) -> Result<Vec<Command>, ServerError> {
+        BufReader::new(File::open(self.dir.path().join("commands.jsonl"))?)
+            .lines()
+            .skip(start)
+            .map(|line| {
+                serde_json::from_str(&line?).map_err(|e| {
+                    ServerError::InternalError(format!("Could not parse session command {}", e))
+                })
+            })
+            .collect()
+    }
+}
+
+fn serialize(command: &Command) -> Result<String, ServerError> {
+    serde_json::to_string(command)
+        .map_err(|e| ServerError::InternalError(format!("Could not serialize command {}", e)))
+}
+
+/// Keeps the commands of the current session
+/// and appends all commands to a JSONL file if a file is configured
+///
+/// The program files of the logged commands are copied to the files directory of the log,
+/// so that the log stays small and the files are only read if a log is configured.
+#[derive(Default)]
+pub struct CommandLog {
+    file: Option<(File, PathBuf)>,
+    session: Option<SessionRecord>,
+}
+impl CommandLog {
+    pub fn open(path: Option<&Path>) -> io::Result<CommandLog> {
+        let file = match path {
+            Some(path) => {
+                fs::create_dir_all(files_dir(path))?;
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                Some((file, files_dir(path)))
+            }
+            None => None,
+        };
+        Ok(CommandLog {
+            file,
+            session: None,
+        })
+    }
+    /// Record a command once it has been executed successfully, creating a control object starts a new session
+    ///
+    /// Commands are recorded while the solver is locked, so that the log has the order of execution.
+    /// The program of a command is given as its file.
+    pub fn record(&mut self, command: Command) -> Result<(), ServerError> {
+        if let Some((file, files)) = &mut self.file {
+            let logged = command.map_program(|program| program.stored(files))?;
+            writeln!(file, "{}", serialize(&logged)?)?;
+            file.flush()?;
+        }
+        if let Command::Create { .. } = command {
+            self.session = Some(SessionRecord::new()?);
+        }
+        if let (Some(session), true) = (&mut self.session, command.is_state()) {
+            session.push(&command)?;
+        }
+        Ok(())
+    }
+    /// The commands of the current session with the texts of their programs
+    pub fn session(&self) -> Result<SessionBundle, ServerError> {
+        let commands = match &self.session {
+            Some(session) => session
+                .commands(0)?
+                .iter()
+                .map(|command| command.map_program(Program::inlined))
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        Ok(SessionBundle { commands })
+    }
+    /// The commands of the current session starting with the command at `start`,
+    /// their programs refer to the files of the session
+    pub fn session_commands(&self, start: usize) -> Result<Vec<Command>, ServerError> {
+        match &self.session {
+            Some(session) => session.commands(start),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+impl Solver {
+    /// Execute a logged command
+    ///
+    /// Theories are created from the registry and program files are written to the workspace.
+    pub fn execute(
+        &mut self,
+        command: &Command,
+        registry: &TheoryRegistry,
+        workspace: &mut Workspace,
+    ) -> Result<(), ServerError> {
+        match command {
+            Command::Create { arguments } => {
+                self.create(arguments.clone())?;
+                *workspace = Workspace::default();
+            }
+            Command::Add { program } => {
+                let file = NamedTempFile::new_in(workspace.dir()?)?;
+                fs::write(file.path(), program)?;
+                self.add_file(file.path())?;
+            }
+            Command::AddProgramRef { program, .. } => {
+                let file = NamedTempFile::new_in(workspace.dir()?)?;
+                program.write_to(file.path())?;
+                self.add_file(file.path())?;
+            }
+            Command::AddFile {
+                name,
+                program,
+                entry,
+            } => {
+                // names of archive members may contain directories but must stay in the workspace
+                if !is_inside(Path::new(name)) {
+                    return Err(ServerError::InternalError(format!(
+                        "Invalid file name {:?}! Expected a path inside the workspace.",
+                        name
+                    )));
+                }
+                let path = workspace.dir()?.join(name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                program.write_to(&path)?;
+                if *entry {
+                    self.add_file(&path)?;
+                }
+            }
+            Command::Facts { facts } => self.add_facts(&json_to_facts(facts)?)?,
+            Command::AddGroundProgram { program } => self.add_ground_program(program)?,
+            Command::Aspif { program } => {
+                let file = NamedTempFile::new()?;
+                program.write_to(file.path())?;
+                self.load_aspif(file.path())?;
+            }
+            Command::Ground { request } => {
+                let (parts, constants) = json_to_ground_request(request)?;
+                self.ground(&parts, &constants)?;
+            }
+            Command::AssignExternal { assignment } => {
+                self.assign_external(&json_to_assignment(assignment)?)?
+            }
+            Command::ReleaseExternal { symbol } => {
+                self.release_external(&json_to_symbol(symbol)?)?
+            }
+            Command::RegisterTheory { name, options } => {
+                let mut theory = registry.create(name)?;
+                theory.configure(name, options)?;
+                self.register_theory(name, theory)?;
+            }
+            Command::SetConfigurationValue { path, value } => {
+                self.set_configuration_value(path, value)?
+            }
+            Command::SetConfiguration { configuration } => {
+                self.set_configuration(&json_to_configuration_result(configuration)?)?;
+            }
+            Command::Solve => self.solve(SolveMode::ASYNC | SolveMode::YIELD, &[])?,
+            Command::SolveWithAssumptions { assumptions } => {
+                self.solve_with_assumptions(&json_to_assumptions(assumptions)?)?
+            }
+            Command::Resume => self.resume()?,
+            Command::Close => self.close()?,
+        }
+        Ok(())
+    }
+    /// Wait for the next model
+    fn wait_model(&mut self) -> Result<ModelResult, ServerError> {
+        loop {
+            match self.model()? {
+                ModelResult::Running => thread::sleep(Duration::from_millis(10)),
+                result => return Ok(result),
+            }
+        }
+    }
+}
+
+/// Replay a command log against a fresh solver and report each command to `out`
+///
+/// After starting or resuming the search the next model is reported.
+/// Only successful commands are logged, so the replay stops at the first failing command
+/// and returns its error.
+pub fn replay(
+    path: &Path,
+    registry: &TheoryRegistry,
+    out: &mut impl Write,
+) -> Result<(), ServerError> {
+    let files = files_dir(path);
+    let mut solver = Solver::None;
+    let mut workspace = Workspace::default();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let command: Command = serde_json::from_str(&line).map_err(|e| {
+            ServerError::InternalError(format!(
+                "Could not parse command in line {} of {}! {}",
+                i + 1,
+                path.display(),
+                e
+            ))
+        })?;
+        // the program files are resolved against the files directory of the log
+        let result = command
+            .map_program(|program| program.resolved(&files))
+            .and_then(|command| solver.execute(&command, registry, &mut workspace))
+            .and_then(|()| match command {
+                Command::Solve | Command::SolveWithAssumptions { .. } | Command::Resume => {
+                    solver.wait_model().map(Some)
+                }
+                _ => Ok(None),
+            });
+        match result {
+            Ok(None) => writeln!(out, "{}: ok", i + 1)?,
+            Ok(Some(ModelResult::Model(model))) => writeln!(
+                out,
+                "{}: model {}",
+                i + 1,
+                String::from_utf8_lossy(&model).trim()
+            )?,
+            Ok(Some(result)) => writeln!(out, "{}: {:?}", i + 1, result)?,
+            Err(e) => {
+                let error = serde_json::to_string(&e).unwrap_or_else(|_| format!("{:?}", e));
+                writeln!(out, "{}: error {}", i + 1, error)?;
+                return Err(ServerError::InternalError(format!(
+                    "Could not replay {}! Command in line {} failed. {}",
+                    path.display(),
+                    i + 1,
+                    e.message()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
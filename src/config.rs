@@ -0,0 +1,198 @@
+use crate::plugin::PluginConfig;
+use crate::utils::ServerError;
+use clap::{Parser, Subcommand};
+use parking_lot::{Condvar, Mutex};
+use rocket::data::{ByteUnit, ToByteUnit};
+use rocket::figment::providers::{Format, Serialized, Toml};
+use rocket::figment::{Figment, Profile};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Command line interface of the server
+#[derive(Debug, Parser)]
+#[clap(
+    name = "cl-server",
+    version,
+    about = "A web server for the clingo solver"
+)]
+pub struct Cli {
+    /// Read the server configuration from a TOML file
+    #[clap(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Port to listen on
+    #[clap(long)]
+    pub port: Option<u16>,
+    /// Maximal number of control objects at the same time, the interactive session and running jobs
+    #[clap(long, value_name = "N")]
+    pub max_sessions: Option<usize>,
+    /// Maximal size of request bodies like 512KiB or 10MiB
+    #[clap(long, value_name = "SIZE")]
+    pub max_body: Option<String>,
+    /// Append the commands of the session to a JSONL file
+    #[clap(long, value_name = "FILE")]
+    pub command_log: Option<PathBuf>,
+    #[clap(subcommand)]
+    pub command: Option<CliCommand>,
+}
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Re-execute a command log against a fresh solver
+    Replay {
+        /// The JSONL command log
+        file: PathBuf,
+    },
+}
+impl Cli {
+    /// Merge the configuration file and the command line options into Rocket's configuration
+    ///
+    /// The configuration file overrides `Rocket.toml` and the environment,
+    /// the command line options override the configuration file.
+    pub fn figment(&self) -> Figment {
+        let mut figment = rocket::Config::figment();
+        if let Some(path) = &self.config {
+            figment = figment.merge(Toml::file(path).profile(Profile::Global));
+        }
+        if let Some(port) = self.port {
+            figment = figment.merge(Serialized::global("port", port));
+        }
+        if let Some(max_sessions) = self.max_sessions {
+            figment = figment.merge(Serialized::global("max_sessions", max_sessions));
+        }
+        if let Some(max_body) = &self.max_body {
+            figment = figment.merge(Serialized::global("max_body", max_body));
+        }
+        if let Some(command_log) = &self.command_log {
+            figment = figment.merge(Serialized::global("command_log", command_log));
+        }
+        figment
+    }
+}
+
+fn default_max_body() -> ByteUnit {
+    512.kibibytes()
+}
+fn default_job_workers() -> usize {
+    2
+}
+fn default_job_queue() -> usize {
+    16
+}
+fn default_job_retention() -> usize {
+    1000
+}
+fn default_arguments() -> Vec<String> {
+    vec!["0".to_string()]
+}
+
+/// The settings of the server next to Rocket's own
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerConfig {
+    /// Maximal size of request bodies
+    #[serde(default = "default_max_body")]
+    pub max_body: ByteUnit,
+    /// Maximal number of control objects at the same time, unlimited if not set
+    ///
+    /// The control object of the interactive session counts once it is created,
+    /// every running job counts until it is finished.
+    #[serde(default)]
+    pub max_sessions: Option<usize>,
+    /// Seconds after which a search is cancelled, unlimited if not set
+    #[serde(default)]
+    pub solve_timeout: Option<u64>,
+    /// Arguments of the control objects created without arguments
+    #[serde(default = "default_arguments")]
+    pub default_arguments: Vec<String>,
+    /// Number of worker threads running jobs
+    #[serde(default = "default_job_workers")]
+    pub job_workers: usize,
+    /// Maximal number of jobs waiting to run
+    #[serde(default = "default_job_queue")]
+    pub job_queue: usize,
+    /// Maximal number of finished jobs that are kept, the oldest are dropped first
+    #[serde(default = "default_job_retention")]
+    pub job_retention: usize,
+    /// JSONL file the commands of the session are appended to, no log if not set
+    #[serde(default)]
+    pub command_log: Option<PathBuf>,
+    /// Directory of the result store, solve runs are not stored if not set
+    #[serde(default)]
+    pub run_dir: Option<PathBuf>,
+    /// Directory of the program library, programs can not be stored if not set
+    #[serde(default)]
+    pub library_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub plugins: Vec<PluginConfig>,
+}
+
+impl ServerConfig {
+    /// Check the settings that can not be expressed by their types
+    pub fn validate(&self) -> Result<(), String> {
+        if self.job_workers == 0 {
+            return Err("Invalid server configuration! job_workers has to be at least 1.".into());
+        }
+        if self.max_sessions == Some(0) {
+            return Err("Invalid server configuration! max_sessions has to be at least 1.".into());
+        }
+        Ok(())
+    }
+}
+
+/// Counts the control objects of the interactive session and the running jobs
+pub struct Sessions {
+    max: Option<usize>,
+    live: Mutex<usize>,
+    released: Condvar,
+    /// Whether the interactive session holds a session, it keeps it once it has a control object
+    interactive: AtomicBool,
+}
+impl Sessions {
+    pub fn new(max: Option<usize>) -> Sessions {
+        Sessions {
+            max,
+            live: Mutex::new(0),
+            released: Condvar::new(),
+            interactive: AtomicBool::new(false),
+        }
+    }
+    /// Reserve the session of the interactive control object without waiting
+    ///
+    /// A new control object replaces the old one in the same session.
+    pub fn acquire_interactive(&self) -> Result<(), ServerError> {
+        let mut live = self.live.lock();
+        if self.interactive.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        if let Some(max) = self.max.filter(|&max| *live >= max) {
+            return Err(ServerError::ServiceUnavailable(format!(
+                "Could not create solver! All {} sessions are in use by jobs.",
+                max
+            )));
+        }
+        *live += 1;
+        self.interactive.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+    /// Reserve a session, blocks until a session is released if the maximal number is reached
+    pub fn acquire(self: &Arc<Self>) -> SessionSlot {
+        let max = self.max.unwrap_or(usize::MAX);
+        let mut live = self.live.lock();
+        while *live >= max {
+            self.released.wait(&mut live);
+        }
+        *live += 1;
+        SessionSlot {
+            sessions: self.clone(),
+        }
+    }
+}
+/// A reserved session, it is released when dropped
+pub struct SessionSlot {
+    sessions: Arc<Sessions>,
+}
+impl Drop for SessionSlot {
+    fn drop(&mut self) {
+        *self.sessions.live.lock() -= 1;
+        self.sessions.released.notify_one();
+    }
+}
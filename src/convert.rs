@@ -1,6 +1,8 @@
-use crate::utils::{ConfigurationResult, ServerError};
-use clingo::{parse_term, Part, Symbol, TruthValue};
+use crate::ground_program::GroundProgram;
+use crate::utils::{ConfigurationResult, ServerError, StatisticsResult};
+use clingo::{parse_term, Part, Symbol, SymbolType, TruthValue};
 use serde_json::Value;
+use std::convert::TryFrom;
 
 pub fn json_to_configuration_result(val: &Value) -> Result<ConfigurationResult, ServerError> {
     match val {
@@ -147,3 +149,282 @@ pub fn json_to_assumptions(val: &Value) -> Result<Vec<(clingo::Symbol, bool)>, S
         )),
     }
 }
+fn json_to_fact_argument(val: &Value) -> Result<Symbol, ServerError> {
+    match val {
+        Value::Number(n) => {
+            let n = n
+                .as_i64()
+                .and_then(|n| i32::try_from(n).ok())
+                .ok_or_else(|| {
+                    ServerError::InternalError(format!("Could not parse fact argument {}", n))
+                })?;
+            Ok(Symbol::create_number(n))
+        }
+        Value::String(s) => {
+            let sym = clingo::parse_term(s)?;
+            Ok(sym)
+        }
+        _ => Err(ServerError::InternalError(
+            "Could not parse facts data".to_string(),
+        )),
+    }
+}
+pub fn is_predicate_name(name: &str) -> bool {
+    let name = name.trim_start_matches('_');
+    match name.chars().next() {
+        Some(c) if c.is_ascii_lowercase() => name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '\''),
+        _ => false,
+    }
+}
+fn json_to_predicate_facts(
+    m: &serde_json::Map<String, Value>,
+    facts: &mut Vec<Symbol>,
+) -> Result<(), ServerError> {
+    let parse_error = || ServerError::InternalError("Could not parse facts data".to_string());
+    let name = match m.get("predicate") {
+        Some(Value::String(s)) => s,
+        _ => return Err(parse_error()),
+    };
+    if !is_predicate_name(name) {
+        return Err(ServerError::InternalError(format!(
+            "Could not parse facts data! Invalid predicate name {}.",
+            name
+        )));
+    }
+    let tuples = match m.get("tuples") {
+        Some(Value::Array(a)) => a,
+        _ => return Err(parse_error()),
+    };
+    for tuple in tuples {
+        let args = match tuple {
+            Value::Array(a) => {
+                let mut args = Vec::with_capacity(a.len());
+                for val in a {
+                    args.push(json_to_fact_argument(val)?);
+                }
+                args
+            }
+            val => vec![json_to_fact_argument(val)?],
+        };
+        facts.push(Symbol::create_function(name, &args, true)?);
+    }
+    Ok(())
+}
+fn json_to_fact(val: &Value, facts: &mut Vec<Symbol>) -> Result<(), ServerError> {
+    match val {
+        Value::String(s) => {
+            let sym = clingo::parse_term(s)?;
+            if sym.symbol_type()? != SymbolType::Function {
+                return Err(ServerError::InternalError(format!(
+                    "Could not parse facts data! {} is not an atom.",
+                    s
+                )));
+            }
+            facts.push(sym);
+            Ok(())
+        }
+        Value::Object(m) => json_to_predicate_facts(m, facts),
+        _ => Err(ServerError::InternalError(
+            "Could not parse facts data".to_string(),
+        )),
+    }
+}
+/// Parse facts given either as `{"predicate": "edge", "tuples": [[1,2],[2,3]]}`
+/// or as an array of such objects and symbol strings like `"edge(1,2)"`.
+pub fn json_to_facts(val: &Value) -> Result<Vec<Symbol>, ServerError> {
+    let mut facts = vec![];
+    match val {
+        Value::Array(a) => {
+            for val in a {
+                json_to_fact(val, &mut facts)?;
+            }
+        }
+        Value::Object(_) => json_to_fact(val, &mut facts)?,
+        _ => {
+            return Err(ServerError::InternalError(
+                "Could not parse facts data".to_string(),
+            ))
+        }
+    }
+    Ok(facts)
+}
+pub fn json_to_ground_program(val: &Value) -> Result<GroundProgram, ServerError> {
+    serde_json::from_value(val.clone()).map_err(|e| {
+        ServerError::InternalError(format!("Could not parse ground program data {}", e))
+    })
+}
+/// Theory options as key value pairs, leading dashes of the keys are removed
+pub fn json_to_theory_options(val: &Value) -> Result<Vec<(String, String)>, ServerError> {
+    match val {
+        Value::Null => Ok(vec![]),
+        Value::Object(map) => {
+            let mut options = vec![];
+            for (key, value) in map {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => n.to_string(),
+                    Value::Bool(b) => b.to_string(),
+                    _ => return Err(ServerError::InternalError(format!(
+                        "Could not parse theory option {}! Expected a string, number or boolean.",
+                        key
+                    ))),
+                };
+                options.push((key.trim_start_matches('-').to_string(), value));
+            }
+            Ok(options)
+        }
+        _ => Err(ServerError::InternalError(
+            "Could not parse theory options! Expected an object.".to_string(),
+        )),
+    }
+}
+pub fn json_to_statistics_paths(val: &Value) -> Result<Vec<String>, ServerError> {
+    let parse_error = || {
+        ServerError::InternalError(
+            "Could not parse statistics paths! Expected an array of strings.".to_string(),
+        )
+    };
+    match val {
+        Value::Array(a) => a
+            .iter()
+            .map(|path| match path {
+                Value::String(s) => Ok(s.clone()),
+                _ => Err(parse_error()),
+            })
+            .collect(),
+        _ => Err(parse_error()),
+    }
+}
+fn json_to_statistics_entry(val: &Value) -> Result<StatisticsResult, ServerError> {
+    match val {
+        Value::Number(n) => n.as_f64().map(StatisticsResult::Value).ok_or_else(|| {
+            ServerError::InternalError(format!("Could not parse user statistics value {}", n))
+        }),
+        Value::Array(a) => Ok(StatisticsResult::Array(
+            a.iter()
+                .map(json_to_statistics_entry)
+                .collect::<Result<_, _>>()?,
+        )),
+        Value::Object(m) => Ok(StatisticsResult::Map(json_to_statistics_map(m)?)),
+        _ => Err(ServerError::InternalError(
+            "Could not parse user statistics! Expected numbers, arrays or objects.".to_string(),
+        )),
+    }
+}
+fn json_to_statistics_map(
+    m: &serde_json::Map<String, Value>,
+) -> Result<Vec<(String, StatisticsResult)>, ServerError> {
+    m.iter()
+        .map(|(k, v)| Ok((k.clone(), json_to_statistics_entry(v)?)))
+        .collect()
+}
+/// Parse a tree of user statistics, the top level has to be an object
+pub fn json_to_user_statistics(
+    val: &Value,
+) -> Result<Vec<(String, StatisticsResult)>, ServerError> {
+    match val {
+        Value::Object(m) => json_to_statistics_map(m),
+        _ => Err(ServerError::InternalError(
+            "Could not parse user statistics! Expected an object.".to_string(),
+        )),
+    }
+}
+/// Parse clingo command line arguments like `["--opt-mode=optN", "-c", "n=5"]`
+/// or an object `{"arguments": [...], "constants": {"n": 5}}`
+///
+/// The constants are passed as `-c` arguments, without `arguments` the `default` ones are used.
+pub fn json_to_arguments(val: &Value, default: &[String]) -> Result<Vec<String>, ServerError> {
+    let parse_error = || {
+        ServerError::InternalError(
+            "Could not parse arguments! Expected an array of strings.".to_string(),
+        )
+    };
+    match val {
+        Value::Array(a) => a
+            .iter()
+            .map(|arg| match arg {
+                Value::String(s) => Ok(s.clone()),
+                _ => Err(parse_error()),
+            })
+            .collect(),
+        Value::Object(m) => {
+            let mut arguments = match m.get("arguments") {
+                Some(val @ Value::Array(_)) => json_to_arguments(val, default)?,
+                Some(_) => return Err(parse_error()),
+                None => default.to_vec(),
+            };
+            if let Some(val) = m.get("constants") {
+                for (name, value) in json_to_constants(val)? {
+                    arguments.push("-c".to_string());
+                    arguments.push(format!("{}={}", name, value));
+                }
+            }
+            Ok(arguments)
+        }
+        _ => Err(parse_error()),
+    }
+}
+/// Parse constants like `{"n": 100, "mode": "fast"}`, string values are parsed as terms
+pub fn json_to_constants(val: &Value) -> Result<Vec<(String, Symbol)>, ServerError> {
+    match val {
+        Value::Object(m) => {
+            let mut constants = Vec::with_capacity(m.len());
+            for (name, value) in m {
+                if !is_predicate_name(name) {
+                    return Err(ServerError::InternalError(format!(
+                        "Could not parse constants! Invalid constant name {}.",
+                        name
+                    )));
+                }
+                let value = match value {
+                    Value::Number(_) | Value::String(_) => {
+                        json_to_fact_argument(value).map_err(|_| {
+                            ServerError::InternalError(format!(
+                                "Could not parse constants! Invalid value {} for constant {}.",
+                                value, name
+                            ))
+                        })?
+                    }
+                    _ => {
+                        return Err(ServerError::InternalError(format!(
+                            "Could not parse constants! Invalid value {} for constant {}.",
+                            value, name
+                        )))
+                    }
+                };
+                constants.push((name.clone(), value));
+            }
+            Ok(constants)
+        }
+        _ => Err(ServerError::InternalError(
+            "Could not parse constants! Expected an object.".to_string(),
+        )),
+    }
+}
+/// Parse the parts to ground, either directly or as `{"parts": {...}, "constants": {...}}`
+pub fn json_to_ground_request(
+    val: &Value,
+) -> Result<(Vec<Part>, Vec<(String, Symbol)>), ServerError> {
+    match val {
+        Value::Object(m) if matches!(m.get("parts"), Some(Value::Object(_))) => {
+            let parts = json_to_parts(&m["parts"])?;
+            let constants = match m.get("constants") {
+                Some(val) => json_to_constants(val)?,
+                None => vec![],
+            };
+            Ok((parts, constants))
+        }
+        _ => Ok((json_to_parts(val)?, vec![])),
+    }
+}
+pub fn json_to_program_ref(val: &Value) -> Result<String, ServerError> {
+    match val.get("program_ref") {
+        Some(Value::String(reference)) => Ok(reference.clone()),
+        _ => Err(ServerError::InternalError(
+            "Could not parse program reference! Expected {\"program_ref\": \"name@version\"}."
+                .to_string(),
+        )),
+    }
+}
@@ -0,0 +1,547 @@
+use crate::utils::ServerError;
+use clingo::{
+    parse_term, Atom, Backend, ExternalType, GroundProgramObserver, SolverLiteral, Symbol,
+    WeightedLiteral,
+};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// A ground logic program in JSON form
+///
+/// Atoms are referred to by positive integer ids chosen by the client,
+/// literals are signed atom ids.
+/// Atoms are only associated with a symbol if they are listed in `atoms`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GroundProgram {
+    #[serde(default)]
+    pub atoms: Vec<AtomDefinition>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub weight_rules: Vec<WeightRule>,
+    #[serde(default)]
+    pub minimize: Vec<Minimize>,
+    #[serde(default)]
+    pub externals: Vec<External>,
+    #[serde(default)]
+    pub projections: Vec<u32>,
+    #[serde(default)]
+    pub outputs: Vec<Output>,
+    #[serde(default)]
+    pub assumptions: Vec<i32>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtomDefinition {
+    pub id: u32,
+    pub symbol: Option<String>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    #[serde(default)]
+    pub choice: bool,
+    pub head: Vec<u32>,
+    #[serde(default)]
+    pub body: Vec<i32>,
+}
+/// Weighted literals are written as `[literal, weight]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightRule {
+    #[serde(default)]
+    pub choice: bool,
+    pub head: Vec<u32>,
+    pub lower_bound: i32,
+    pub body: Vec<(i32, i32)>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Minimize {
+    #[serde(default)]
+    pub priority: i32,
+    pub literals: Vec<(i32, i32)>,
+}
+/// A symbol that is shown whenever its condition holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Output {
+    pub symbol: String,
+    #[serde(default)]
+    pub condition: Vec<i32>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct External {
+    pub atom: u32,
+    #[serde(default)]
+    pub value: ExternalValue,
+}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ExternalValue {
+    Free,
+    True,
+    False,
+    Release,
+}
+impl Default for ExternalValue {
+    fn default() -> Self {
+        ExternalValue::Free
+    }
+}
+impl From<ExternalType> for ExternalValue {
+    fn from(value: ExternalType) -> Self {
+        match value {
+            ExternalType::Free => ExternalValue::Free,
+            ExternalType::True => ExternalValue::True,
+            ExternalType::False => ExternalValue::False,
+            ExternalType::Release => ExternalValue::Release,
+        }
+    }
+}
+impl From<ExternalValue> for ExternalType {
+    fn from(value: ExternalValue) -> Self {
+        match value {
+            ExternalValue::Free => ExternalType::Free,
+            ExternalValue::True => ExternalType::True,
+            ExternalValue::False => ExternalType::False,
+            ExternalValue::Release => ExternalType::Release,
+        }
+    }
+}
+
+/// Maps the atom ids of a validated `GroundProgram` to the atoms of a backend
+/// The atoms of the ids of the ground programs added to a control object
+pub type AtomIds = HashMap<u32, Atom>;
+
+struct BackendAtoms<'a, 'b> {
+    backend: &'a mut Backend<'b>,
+    atoms: &'a mut AtomIds,
+}
+impl<'a, 'b> BackendAtoms<'a, 'b> {
+    fn atom(&mut self, id: u32) -> Result<Atom, ServerError> {
+        if let Some(atm) = self.atoms.get(&id) {
+            return Ok(*atm);
+        }
+        let atm = self.backend.add_atom(None)?;
+        self.atoms.insert(id, atm);
+        Ok(atm)
+    }
+    fn atoms(&mut self, ids: &[u32]) -> Result<Vec<Atom>, ServerError> {
+        ids.iter().map(|id| self.atom(*id)).collect()
+    }
+    fn literal(&mut self, lit: i32) -> Result<SolverLiteral, ServerError> {
+        let lit_ = SolverLiteral::from(self.atom(lit.unsigned_abs())?);
+        if lit < 0 {
+            Ok(lit_.negate())
+        } else {
+            Ok(lit_)
+        }
+    }
+    fn literals(&mut self, lits: &[i32]) -> Result<Vec<SolverLiteral>, ServerError> {
+        lits.iter().map(|lit| self.literal(*lit)).collect()
+    }
+    fn weighted_literals(
+        &mut self,
+        lits: &[(i32, i32)],
+    ) -> Result<Vec<WeightedLiteral>, ServerError> {
+        lits.iter()
+            .map(|(lit, weight)| Ok(WeightedLiteral::new(self.literal(*lit)?, *weight)))
+            .collect()
+    }
+}
+
+/// A program whose ids and literals are valid together with its parsed symbols
+pub struct ValidatedProgram<'a> {
+    program: &'a GroundProgram,
+    atoms: Vec<Option<Symbol>>,
+    outputs: Vec<Symbol>,
+}
+fn check_atoms(ids: &[u32]) -> Result<(), ServerError> {
+    if ids.contains(&0) {
+        return Err(ServerError::InternalError(
+            "Could not add ground program! 0 is not a valid atom.".to_string(),
+        ));
+    }
+    Ok(())
+}
+fn check_literals<'a>(mut lits: impl Iterator<Item = &'a i32>) -> Result<(), ServerError> {
+    if lits.any(|lit| *lit == 0) {
+        return Err(ServerError::InternalError(
+            "Could not add ground program! 0 is not a valid literal.".to_string(),
+        ));
+    }
+    Ok(())
+}
+impl GroundProgram {
+    /// Check all statements before anything is added, so that an invalid program adds nothing
+    pub fn validate(&self) -> Result<ValidatedProgram<'_>, ServerError> {
+        let mut ids = HashSet::new();
+        let mut atoms = Vec::with_capacity(self.atoms.len());
+        for def in &self.atoms {
+            if def.id == 0 || !ids.insert(def.id) {
+                return Err(ServerError::InternalError(format!(
+                    "Could not add ground program! Invalid atom definition {}.",
+                    def.id
+                )));
+            }
+            atoms.push(match &def.symbol {
+                Some(s) => Some(parse_term(s)?),
+                None => None,
+            });
+        }
+        for rule in &self.rules {
+            check_atoms(&rule.head)?;
+            check_literals(rule.body.iter())?;
+        }
+        for rule in &self.weight_rules {
+            check_atoms(&rule.head)?;
+            check_literals(rule.body.iter().map(|(lit, _)| lit))?;
+        }
+        for minimize in &self.minimize {
+            check_literals(minimize.literals.iter().map(|(lit, _)| lit))?;
+        }
+        let externals: Vec<u32> = self.externals.iter().map(|e| e.atom).collect();
+        check_atoms(&externals)?;
+        check_atoms(&self.projections)?;
+        let mut outputs = Vec::with_capacity(self.outputs.len());
+        for output in &self.outputs {
+            outputs.push(parse_term(&output.symbol)?);
+            check_literals(output.condition.iter())?;
+        }
+        check_literals(self.assumptions.iter())?;
+        Ok(ValidatedProgram {
+            program: self,
+            atoms,
+            outputs,
+        })
+    }
+}
+impl<'a> ValidatedProgram<'a> {
+    /// Add the statements of the program to a backend
+    ///
+    /// The ids refer to the atoms in `ids` of the programs added before,
+    /// an id can not be redefined as another atom.
+    pub fn add_to_backend(
+        self,
+        backend: &mut Backend,
+        ids: &mut AtomIds,
+    ) -> Result<(), ServerError> {
+        let program = self.program;
+        let mut atoms = BackendAtoms {
+            backend,
+            atoms: ids,
+        };
+        // atoms with symbols have to be known before they are used in statements
+        for (def, symbol) in program.atoms.iter().zip(self.atoms) {
+            let known = atoms.atoms.get(&def.id).copied();
+            let atm = match (known, symbol) {
+                (Some(atm), None) => atm,
+                (known, symbol) => {
+                    let atm = atoms.backend.add_atom(symbol)?;
+                    if known.map_or(false, |known| known != atm) {
+                        return Err(ServerError::InternalError(format!(
+                            "Could not add ground program! Atom {} is already defined as another atom.",
+                            def.id
+                        )));
+                    }
+                    atm
+                }
+            };
+            atoms.atoms.insert(def.id, atm);
+        }
+        for rule in &program.rules {
+            let head = atoms.atoms(&rule.head)?;
+            let body = atoms.literals(&rule.body)?;
+            atoms.backend.rule(rule.choice, &head, &body)?;
+        }
+        for rule in &program.weight_rules {
+            let head = atoms.atoms(&rule.head)?;
+            let body = atoms.weighted_literals(&rule.body)?;
+            atoms
+                .backend
+                .weight_rule(rule.choice, &head, rule.lower_bound, &body)?;
+        }
+        for minimize in &program.minimize {
+            let literals = atoms.weighted_literals(&minimize.literals)?;
+            atoms.backend.minimize(minimize.priority, &literals)?;
+        }
+        for external in &program.externals {
+            let atm = atoms.atom(external.atom)?;
+            atoms.backend.external(atm, external.value.into())?;
+        }
+        if !program.projections.is_empty() {
+            let projections = atoms.atoms(&program.projections)?;
+            atoms.backend.project(&projections)?;
+        }
+        for (output, symbol) in program.outputs.iter().zip(self.outputs) {
+            let atm = atoms.backend.add_atom(Some(symbol))?;
+            let condition = atoms.literals(&output.condition)?;
+            atoms.backend.rule(false, &[atm], &condition)?;
+        }
+        if !program.assumptions.is_empty() {
+            let assumptions = atoms.literals(&program.assumptions)?;
+            atoms.backend.assume(&assumptions)?;
+        }
+        Ok(())
+    }
+}
+
+/// The ground program recorded for the current control object
+#[derive(Default)]
+pub struct GroundProgramRecord {
+    pub program: GroundProgram,
+}
+/// Records the statements produced by the grounder
+pub struct GroundProgramRecorder {
+    record: Arc<Mutex<GroundProgramRecord>>,
+}
+impl GroundProgramRecorder {
+    pub fn new(record: Arc<Mutex<GroundProgramRecord>>) -> GroundProgramRecorder {
+        GroundProgramRecorder { record }
+    }
+}
+fn atom_id(atm: &Atom) -> u32 {
+    SolverLiteral::from(*atm).get_integer() as u32
+}
+fn atom_ids(atoms: &[Atom]) -> Vec<u32> {
+    atoms.iter().map(atom_id).collect()
+}
+fn literal_ids(literals: &[SolverLiteral]) -> Vec<i32> {
+    literals.iter().map(|lit| lit.get_integer()).collect()
+}
+fn weighted_literal_ids(literals: &[WeightedLiteral]) -> Vec<(i32, i32)> {
+    literals
+        .iter()
+        .map(|wl| (wl.literal().get_integer(), wl.weight()))
+        .collect()
+}
+impl GroundProgramObserver for GroundProgramRecorder {
+    fn rule(&mut self, choice: bool, head: &[Atom], body: &[SolverLiteral]) -> bool {
+        self.record.lock().program.rules.push(Rule {
+            choice,
+            head: atom_ids(head),
+            body: literal_ids(body),
+        });
+        true
+    }
+    fn weight_rule(
+        &mut self,
+        choice: bool,
+        head: &[Atom],
+        lower_bound: i32,
+        body: &[WeightedLiteral],
+    ) -> bool {
+        self.record.lock().program.weight_rules.push(WeightRule {
+            choice,
+            head: atom_ids(head),
+            lower_bound,
+            body: weighted_literal_ids(body),
+        });
+        true
+    }
+    fn minimize(&mut self, priority: i32, literals: &[WeightedLiteral]) -> bool {
+        self.record.lock().program.minimize.push(Minimize {
+            priority,
+            literals: weighted_literal_ids(literals),
+        });
+        true
+    }
+    fn project(&mut self, atoms: &[Atom]) -> bool {
+        self.record
+            .lock()
+            .program
+            .projections
+            .extend(atom_ids(atoms));
+        true
+    }
+    fn output_atom(&mut self, symbol: Symbol, atom: Atom) -> bool {
+        let mut record = self.record.lock();
+        let id = atom_id(&atom);
+        // atom 0 marks symbols that are facts
+        if id == 0 {
+            record.program.outputs.push(Output {
+                symbol: symbol.to_string(),
+                condition: vec![],
+            });
+        } else {
+            record.program.atoms.push(AtomDefinition {
+                id,
+                symbol: Some(symbol.to_string()),
+            });
+        }
+        true
+    }
+    fn output_term(&mut self, symbol: Symbol, condition: &[SolverLiteral]) -> bool {
+        self.record.lock().program.outputs.push(Output {
+            symbol: symbol.to_string(),
+            condition: literal_ids(condition),
+        });
+        true
+    }
+    fn external(&mut self, atom: Atom, type_: ExternalType) -> bool {
+        self.record.lock().program.externals.push(External {
+            atom: atom_id(&atom),
+            value: type_.into(),
+        });
+        true
+    }
+    fn assume(&mut self, literals: &[SolverLiteral]) -> bool {
+        self.record
+            .lock()
+            .program
+            .assumptions
+            .extend(literal_ids(literals));
+        true
+    }
+}
+
+fn write_aspif_weighted_literals(out: &mut String, literals: &[(i32, i32)]) {
+    write!(out, " {}", literals.len()).unwrap();
+    for (lit, weight) in literals {
+        write!(out, " {} {}", lit, weight).unwrap();
+    }
+}
+fn write_aspif_list<T: std::fmt::Display>(out: &mut String, list: &[T]) {
+    write!(out, " {}", list.len()).unwrap();
+    for e in list {
+        write!(out, " {}", e).unwrap();
+    }
+}
+impl GroundProgram {
+    /// Write the program as a single step in aspif format
+    pub fn to_aspif(&self) -> String {
+        let mut out = String::from("asp 1 0 0\n");
+        for rule in &self.rules {
+            write!(out, "1 {}", rule.choice as u8).unwrap();
+            write_aspif_list(&mut out, &rule.head);
+            out.push_str(" 0");
+            write_aspif_list(&mut out, &rule.body);
+            out.push('\n');
+        }
+        for rule in &self.weight_rules {
+            write!(out, "1 {}", rule.choice as u8).unwrap();
+            write_aspif_list(&mut out, &rule.head);
+            write!(out, " 1 {}", rule.lower_bound).unwrap();
+            write_aspif_weighted_literals(&mut out, &rule.body);
+            out.push('\n');
+        }
+        for minimize in &self.minimize {
+            write!(out, "2 {}", minimize.priority).unwrap();
+            write_aspif_weighted_literals(&mut out, &minimize.literals);
+            out.push('\n');
+        }
+        if !self.projections.is_empty() {
+            out.push('3');
+            write_aspif_list(&mut out, &self.projections);
+            out.push('\n');
+        }
+        for def in &self.atoms {
+            if let Some(symbol) = &def.symbol {
+                writeln!(out, "4 {} {} 1 {}", symbol.len(), symbol, def.id).unwrap();
+            }
+        }
+        for output in &self.outputs {
+            write!(out, "4 {} {}", output.symbol.len(), output.symbol).unwrap();
+            write_aspif_list(&mut out, &output.condition);
+            out.push('\n');
+        }
+        for external in &self.externals {
+            let value = match external.value {
+                ExternalValue::Free => 0,
+                ExternalValue::True => 1,
+                ExternalValue::False => 2,
+                ExternalValue::Release => 3,
+            };
+            writeln!(out, "5 {} {}", external.atom, value).unwrap();
+        }
+        if !self.assumptions.is_empty() {
+            out.push('6');
+            write_aspif_list(&mut out, &self.assumptions);
+            out.push('\n');
+        }
+        out.push_str("0\n");
+        out
+    }
+    /// Write the program in a human readable text format
+    ///
+    /// Atoms without a symbol are written as `#aux(id)`.
+    pub fn to_text(&self) -> String {
+        let symbols: HashMap<u32, &str> = self
+            .atoms
+            .iter()
+            .filter_map(|def| def.symbol.as_deref().map(|s| (def.id, s)))
+            .collect();
+        let atom = |id: &u32| match symbols.get(id) {
+            Some(s) => s.to_string(),
+            None => format!("#aux({})", id),
+        };
+        let literal = |lit: &i32| {
+            let a = atom(&lit.unsigned_abs());
+            if *lit < 0 {
+                format!("not {}", a)
+            } else {
+                a
+            }
+        };
+        let head = |choice: bool, head: &[u32]| {
+            let atoms: Vec<String> = head.iter().map(atom).collect();
+            if choice {
+                format!("{{{}}}", atoms.join(";"))
+            } else {
+                atoms.join(";")
+            }
+        };
+        let rule = |head: String, body: String| {
+            if body.is_empty() {
+                format!("{}.\n", head)
+            } else if head.is_empty() {
+                format!(":- {}.\n", body)
+            } else {
+                format!("{} :- {}.\n", head, body)
+            }
+        };
+        let mut out = String::new();
+        for r in &self.rules {
+            let body: Vec<String> = r.body.iter().map(literal).collect();
+            out.push_str(&rule(head(r.choice, &r.head), body.join(", ")));
+        }
+        for r in &self.weight_rules {
+            let elements: Vec<String> = r
+                .body
+                .iter()
+                .enumerate()
+                .map(|(i, (lit, weight))| format!("{},{}: {}", weight, i, literal(lit)))
+                .collect();
+            let body = format!("{} <= #sum{{{}}}", r.lower_bound, elements.join("; "));
+            out.push_str(&rule(head(r.choice, &r.head), body));
+        }
+        for m in &self.minimize {
+            let elements: Vec<String> = m
+                .literals
+                .iter()
+                .enumerate()
+                .map(|(i, (lit, weight))| {
+                    format!("{}@{},{}: {}", weight, m.priority, i, literal(lit))
+                })
+                .collect();
+            writeln!(out, "#minimize{{{}}}.", elements.join("; ")).unwrap();
+        }
+        for a in &self.projections {
+            writeln!(out, "#project {}.", atom(a)).unwrap();
+        }
+        for output in &self.outputs {
+            let condition: Vec<String> = output.condition.iter().map(literal).collect();
+            if condition.is_empty() {
+                writeln!(out, "#show {}.", output.symbol).unwrap();
+            } else {
+                writeln!(out, "#show {} : {}.", output.symbol, condition.join(", ")).unwrap();
+            }
+        }
+        for external in &self.externals {
+            let value = format!("{:?}", external.value).to_lowercase();
+            writeln!(out, "#external {}. [{}]", atom(&external.atom), value).unwrap();
+        }
+        if !self.assumptions.is_empty() {
+            let assumptions: Vec<String> = self.assumptions.iter().map(literal).collect();
+            writeln!(out, "% assumptions: {}", assumptions.join(", ")).unwrap();
+        }
+        out
+    }
+}
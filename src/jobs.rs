@@ -0,0 +1,312 @@
+use crate::command::Command;
+use crate::config::Sessions;
+use crate::convert::{json_to_arguments, json_to_theory_options};
+use crate::runs::RunModel;
+use crate::theory::TheoryRegistry;
+use crate::upload::Workspace;
+use crate::utils::{ModelResult, ServerError, SolveSummary, Solver, StatisticsResult};
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::any::Any;
+use std::collections::{BTreeMap, VecDeque};
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// A complete problem to solve in the background
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobRequest {
+    pub program: String,
+    /// Arguments of the control object like for `POST /create`
+    #[serde(default)]
+    pub arguments: Option<Value>,
+    /// Parts to ground like for `/ground`, the base part if not set
+    #[serde(default)]
+    pub parts: Option<Value>,
+    #[serde(default)]
+    pub theory: Option<String>,
+    #[serde(default)]
+    pub theory_options: Option<Value>,
+    /// Configuration like for `/set_configuration`
+    #[serde(default)]
+    pub configuration: Option<Value>,
+    #[serde(default)]
+    pub assumptions: Option<Value>,
+    /// Maximal number of models to collect, all models if not set
+    #[serde(default)]
+    pub models: Option<NonZeroUsize>,
+}
+impl JobRequest {
+    /// The commands of a session solving the problem
+    fn commands(&self, default_arguments: &[String]) -> Result<Vec<Command>, ServerError> {
+        let arguments = match &self.arguments {
+            Some(val) => json_to_arguments(val, default_arguments)?,
+            None => default_arguments.to_vec(),
+        };
+        let mut commands = vec![Command::Create { arguments }];
+        if let Some(name) = &self.theory {
+            let options = match &self.theory_options {
+                Some(val) => json_to_theory_options(val)?,
+                None => vec![],
+            };
+            commands.push(Command::RegisterTheory {
+                name: name.clone(),
+                options,
+            });
+        }
+        if let Some(configuration) = &self.configuration {
+            commands.push(Command::SetConfiguration {
+                configuration: configuration.clone(),
+            });
+        }
+        commands.push(Command::Add {
+            program: self.program.clone(),
+        });
+        commands.push(Command::Ground {
+            request: self
+                .parts
+                .clone()
+                .unwrap_or_else(|| serde_json::json!({"base": []})),
+        });
+        commands.push(match &self.assumptions {
+            Some(assumptions) => Command::SolveWithAssumptions {
+                assumptions: assumptions.clone(),
+            },
+            None => Command::Solve,
+        });
+        Ok(commands)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+}
+
+/// The state of a job, the models are added while it is running
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: u64,
+    pub status: JobStatus,
+    pub models: Vec<RunModel>,
+    pub result: Option<SolveSummary>,
+    pub statistics: Option<StatisticsResult>,
+    pub error: Option<Value>,
+}
+
+/// The submitted jobs, only the latest `retention` finished jobs are kept
+struct JobTable {
+    jobs: BTreeMap<u64, Job>,
+    finished: VecDeque<u64>,
+    retention: usize,
+}
+type Jobs = Arc<Mutex<JobTable>>;
+struct QueuedJob {
+    id: u64,
+    commands: Vec<Command>,
+    models: Option<NonZeroUsize>,
+}
+
+/// Runs submitted jobs on a pool of worker threads
+///
+/// At most `depth` jobs wait in the queue, every running job counts as a session
+/// and at most `retention` finished jobs are kept.
+/// The workers stop when the queue is dropped.
+pub struct JobQueue {
+    sender: Mutex<SyncSender<QueuedJob>>,
+    jobs: Jobs,
+    next: AtomicU64,
+    depth: usize,
+    default_arguments: Vec<String>,
+}
+impl JobQueue {
+    pub fn start(
+        workers: usize,
+        depth: usize,
+        retention: usize,
+        registry: Arc<TheoryRegistry>,
+        sessions: Arc<Sessions>,
+        timeout: Option<Duration>,
+        default_arguments: Vec<String>,
+    ) -> JobQueue {
+        let (sender, receiver) = mpsc::sync_channel(depth);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let jobs: Jobs = Arc::new(Mutex::new(JobTable {
+            jobs: BTreeMap::new(),
+            finished: VecDeque::new(),
+            retention,
+        }));
+        for _ in 0..workers {
+            let worker = Worker {
+                receiver: receiver.clone(),
+                jobs: jobs.clone(),
+                registry: registry.clone(),
+                sessions: sessions.clone(),
+                timeout,
+            };
+            thread::spawn(move || worker.run());
+        }
+        JobQueue {
+            sender: Mutex::new(sender),
+            jobs,
+            next: AtomicU64::new(1),
+            depth,
+            default_arguments,
+        }
+    }
+    /// Queue a job, fails if the queue is full
+    pub fn submit(&self, request: &JobRequest) -> Result<Job, ServerError> {
+        let commands = request.commands(&self.default_arguments)?;
+        let id = self.next.fetch_add(1, Ordering::SeqCst);
+        let job = Job {
+            id,
+            status: JobStatus::Queued,
+            models: vec![],
+            result: None,
+            statistics: None,
+            error: None,
+        };
+        self.jobs.lock().jobs.insert(id, job.clone());
+        let queued = QueuedJob {
+            id,
+            commands,
+            models: request.models,
+        };
+        let sent = self.sender.lock().try_send(queued);
+        match sent {
+            Ok(()) => Ok(job),
+            Err(e) => {
+                self.jobs.lock().jobs.remove(&id);
+                Err(match e {
+                    TrySendError::Full(_) => ServerError::ServiceUnavailable(format!(
+                        "Job queue is full! {} jobs are waiting.",
+                        self.depth
+                    )),
+                    TrySendError::Disconnected(_) => ServerError::ServiceUnavailable(
+                        "Job queue is stopped! No workers are running.".to_string(),
+                    ),
+                })
+            }
+        }
+    }
+    pub fn get(&self, id: u64) -> Result<Job, ServerError> {
+        self.jobs
+            .lock()
+            .jobs
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| ServerError::NotFound(format!("Unknown job {}!", id)))
+    }
+}
+
+struct Worker {
+    receiver: Arc<Mutex<Receiver<QueuedJob>>>,
+    jobs: Jobs,
+    registry: Arc<TheoryRegistry>,
+    sessions: Arc<Sessions>,
+    timeout: Option<Duration>,
+}
+impl Worker {
+    fn run(self) {
+        loop {
+            // the lock is released before the job is run
+            let job = self.receiver.lock().recv();
+            let job = match job {
+                Ok(job) => job,
+                Err(_) => return,
+            };
+            // wait for a free session, it is released once the job is done
+            let slot = self.sessions.acquire();
+            self.update(job.id, |entry| entry.status = JobStatus::Running);
+            // a panic of the solver fails the job instead of the worker
+            let result = match panic::catch_unwind(AssertUnwindSafe(|| self.solve(&job))) {
+                Ok(result) => result,
+                Err(payload) => Err(ServerError::InternalError(format!(
+                    "Job {} failed! The solver panicked: {}",
+                    job.id,
+                    panic_message(&*payload)
+                ))),
+            };
+            drop(slot);
+            self.finish(job.id, result);
+        }
+    }
+    fn update(&self, id: u64, f: impl FnOnce(&mut Job)) {
+        if let Some(entry) = self.jobs.lock().jobs.get_mut(&id) {
+            f(entry);
+        }
+    }
+    /// Set the final status of a job and drop the oldest finished jobs beyond the retention
+    fn finish(&self, id: u64, result: Result<(), ServerError>) {
+        let mut table = self.jobs.lock();
+        if let Some(entry) = table.jobs.get_mut(&id) {
+            match result {
+                Ok(()) => entry.status = JobStatus::Finished,
+                Err(e) => {
+                    entry.status = JobStatus::Failed;
+                    entry.error = serde_json::to_value(&e).ok();
+                }
+            }
+            table.finished.push_back(id);
+        }
+        while table.finished.len() > table.retention {
+            if let Some(old) = table.finished.pop_front() {
+                table.jobs.remove(&old);
+            }
+        }
+    }
+    fn solve(&self, job: &QueuedJob) -> Result<(), ServerError> {
+        let mut solver = Solver::None;
+        let mut workspace = Workspace::default();
+        for command in &job.commands {
+            solver.execute(command, &self.registry, &mut workspace)?;
+        }
+        let mut models = 0;
+        loop {
+            if let Some(timeout) = self.timeout {
+                solver.check_timeout(timeout)?;
+            }
+            match solver.model()? {
+                ModelResult::Running => thread::sleep(Duration::from_millis(10)),
+                ModelResult::Model(model) => {
+                    let model = RunModel {
+                        model: String::from_utf8_lossy(&model).into_owned(),
+                        costs: solver.costs(),
+                    };
+                    self.update(job.id, |entry| entry.models.push(model));
+                    models += 1;
+                    if job.models.map_or(false, |max| models >= max.get()) {
+                        break;
+                    }
+                    solver.resume()?;
+                }
+                ModelResult::Done => break,
+            }
+        }
+        let result = solver.solve_summary();
+        solver.close()?;
+        let statistics = solver.statistics()?;
+        self.update(job.id, |entry| {
+            entry.result = result;
+            entry.statistics = Some(statistics);
+        });
+        Ok(())
+    }
+}
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
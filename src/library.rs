@@ -0,0 +1,133 @@
+use crate::utils::ServerError;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A stored version of a program
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramVersion {
+    pub name: String,
+    pub version: String,
+    pub sha256: String,
+    pub size: usize,
+}
+impl ProgramVersion {
+    /// The reference of this version like `queens@v3`
+    pub fn reference(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+}
+
+/// Named and versioned programs stored on the server's disk
+///
+/// Every program has a directory holding its versions `v1.lp`, `v2.lp`, ...
+/// and an `index.json` with their hashes.
+/// Stored programs have to survive a restart, so there is no library without a configured directory.
+pub struct ProgramLibrary {
+    dir: Option<PathBuf>,
+}
+impl ProgramLibrary {
+    /// Open the library in `dir`, without directory every access to the library fails
+    pub fn open(dir: Option<&Path>) -> Result<ProgramLibrary, ServerError> {
+        if let Some(dir) = dir {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(ProgramLibrary {
+            dir: dir.map(Path::to_path_buf),
+        })
+    }
+    fn dir(&self) -> Result<&Path, ServerError> {
+        self.dir.as_deref().ok_or_else(|| {
+            ServerError::InternalError(
+                "No program library! The server is started without library_dir.".to_string(),
+            )
+        })
+    }
+    /// The versions of a program, oldest first
+    pub fn versions(&self, name: &str) -> Result<Vec<ProgramVersion>, ServerError> {
+        check_name(name)?;
+        let index = self.dir()?.join(name).join("index.json");
+        if !index.exists() {
+            return Err(ServerError::InternalError(format!(
+                "Unknown program {}!",
+                name
+            )));
+        }
+        let json = fs::read_to_string(index)?;
+        serde_json::from_str(&json).map_err(|e| {
+            ServerError::InternalError(format!("Could not read index of program {}! {}", name, e))
+        })
+    }
+    /// Store a program, storing the text of the latest version again does not create a new version
+    pub fn store(&mut self, name: &str, text: &str) -> Result<ProgramVersion, ServerError> {
+        check_name(name)?;
+        let dir = self.dir()?.join(name);
+        let mut versions = if dir.join("index.json").exists() {
+            self.versions(name)?
+        } else {
+            fs::create_dir_all(&dir)?;
+            vec![]
+        };
+        let sha256 = format!("{:x}", Sha256::digest(text.as_bytes()));
+        if let Some(latest) = versions.last() {
+            if latest.sha256 == sha256 {
+                return Ok(latest.clone());
+            }
+        }
+        let version = ProgramVersion {
+            name: name.to_string(),
+            version: format!("v{}", versions.len() + 1),
+            sha256,
+            size: text.len(),
+        };
+        fs::write(dir.join(format!("{}.lp", version.version)), text)?;
+        versions.push(version.clone());
+        let json = serde_json::to_string_pretty(&versions).map_err(|e| {
+            ServerError::InternalError(format!("Could not write index of program {}! {}", name, e))
+        })?;
+        // replace the index at once so that readers never see a partial index
+        let index = dir.join("index.json.tmp");
+        fs::write(&index, json)?;
+        fs::rename(index, dir.join("index.json"))?;
+        Ok(version)
+    }
+    /// Resolve a reference `name@version` or `name` for the latest version to the stored file
+    pub fn resolve(&self, reference: &str) -> Result<(ProgramVersion, PathBuf), ServerError> {
+        let (name, version) = match reference.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (reference, None),
+        };
+        let versions = self.versions(name)?;
+        let found = match version {
+            Some(version) => versions.iter().find(|v| v.version == version),
+            None => versions.last(),
+        };
+        match found {
+            Some(found) => {
+                let path = self.dir()?.join(name).join(format!("{}.lp", found.version));
+                Ok((found.clone(), path))
+            }
+            None => Err(ServerError::InternalError(format!(
+                "Unknown version {} of program {}! The latest version is v{}.",
+                version.unwrap_or(""),
+                name,
+                versions.len()
+            ))),
+        }
+    }
+}
+
+fn check_name(name: &str) -> Result<(), ServerError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(ServerError::InternalError(format!(
+            "Invalid program name {}! Expected letters, digits, '_' or '-'.",
+            name
+        )))
+    }
+}
@@ -3,19 +3,52 @@ extern crate rocket;
 #[macro_use]
 extern crate serde_derive;
 
+mod command;
+mod config;
 mod convert;
+mod ground_program;
+mod jobs;
+mod library;
+mod plugin;
+mod runs;
+mod theory;
+mod upload;
 mod utils;
+use clap::Parser;
 use clingo::SolveMode;
+use command::{Command, CommandLog, Program, SessionBundle};
+use config::{Cli, CliCommand, ServerConfig, Sessions};
 use convert::{
-    json_to_assignment, json_to_assumptions, json_to_configuration_result, json_to_parts,
-    json_to_symbol,
+    json_to_arguments, json_to_assignment, json_to_assumptions, json_to_configuration_result,
+    json_to_facts, json_to_ground_program, json_to_ground_request, json_to_program_ref,
+    json_to_statistics_paths, json_to_symbol, json_to_theory_options, json_to_user_statistics,
 };
+use ground_program::{GroundProgramRecord, GroundProgramRecorder};
+use jobs::{Job, JobQueue, JobRequest};
+use library::{ProgramLibrary, ProgramVersion};
 use parking_lot::Mutex;
-use rocket::data::ToByteUnit;
+use plugin::PluginInfo;
+use rocket::data::ByteUnit;
+use rocket::figment::Figment;
+use rocket::form::{Errors, Form};
+use rocket::fs::TempFile;
+use rocket::http::{ContentType, Status};
 use rocket::serde::json::Json;
-use rocket::{Data, State};
+use rocket::{Build, Data, Rocket, State};
+use runs::RunStore;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
 use std::sync::Arc;
-use utils::{ConfigurationResult, ModelResult, RequestId, ServerError, Solver, StatisticsResult};
+use std::thread;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use theory::{TheoryInstance, TheoryRegistry};
+use upload::{workspace_file_name, UploadHeaders, Workspace};
+use utils::{
+    read_body, too_large, ConfigurationListing, ModelResult, RequestId, ServerError, Solver,
+    StatisticsResult, StatisticsView, TheoryAtomResult,
+};
 
 #[cfg(test)]
 mod test;
@@ -25,66 +58,481 @@ fn index(id: &RequestId) -> String {
     format!("This is request #{}.", id.0)
 }
 #[get("/create")]
-fn create(state: &State<Arc<Mutex<Solver>>>) -> Result<String, ServerError> {
+fn create(
+    state: &State<Arc<Mutex<Solver>>>,
+    record: &State<Arc<Mutex<GroundProgramRecord>>>,
+    workspace: &State<Arc<Mutex<Workspace>>>,
+    log: &State<Mutex<CommandLog>>,
+    runs: &State<Arc<Mutex<RunStore>>>,
+    sessions: &State<Arc<Sessions>>,
+    config: &State<ServerConfig>,
+) -> Result<String, ServerError> {
+    sessions.acquire_interactive()?;
+    create_solver(
+        state,
+        record,
+        workspace,
+        log,
+        runs,
+        config.default_arguments.clone(),
+    )
+}
+#[post("/create", format = "application/json", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
+async fn create_with_arguments(
+    state: &State<Arc<Mutex<Solver>>>,
+    record: &State<Arc<Mutex<GroundProgramRecord>>>,
+    workspace: &State<Arc<Mutex<Workspace>>>,
+    log: &State<Mutex<CommandLog>>,
+    runs: &State<Arc<Mutex<RunStore>>>,
+    sessions: &State<Arc<Sessions>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
+        .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
+
+    let arguments = json_to_arguments(&val, &config.default_arguments)?;
+    sessions.acquire_interactive()?;
+    create_solver(state, record, workspace, log, runs, arguments)
+}
+fn create_solver(
+    state: &Arc<Mutex<Solver>>,
+    record: &Arc<Mutex<GroundProgramRecord>>,
+    workspace: &Arc<Mutex<Workspace>>,
+    log: &Mutex<CommandLog>,
+    runs: &Arc<Mutex<RunStore>>,
+    arguments: Vec<String>,
+) -> Result<String, ServerError> {
     let mut solver = state.lock();
-    solver.create(vec!["0".to_string()])?;
+    solver.create(arguments.clone())?;
+    // record the ground program of the new control object
+    *record.lock() = GroundProgramRecord::default();
+    solver.register_observer(GroundProgramRecorder::new(record.clone()))?;
+    solver.record_runs(runs.clone())?;
+    // the new session starts with an empty workspace
+    *workspace.lock() = Workspace::default();
+    log.lock().record(Command::Create { arguments })?;
+    runs.lock().new_session();
     Ok("Created clingo Solver.".to_string())
 }
-#[post("/add", data = "<data>")]
-async fn add(state: &State<Arc<Mutex<Solver>>>, data: Data<'_>) -> Result<String, ServerError> {
-    let ds = data.open(512.kibibytes());
-    let cap = ds.into_string().await?;
+#[get("/session")]
+fn current_session(runs: &State<Arc<Mutex<RunStore>>>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "session": runs.lock().session() }))
+}
+#[get("/runs/<id>")]
+fn run(runs: &State<Arc<Mutex<RunStore>>>, id: &str) -> Result<(ContentType, String), ServerError> {
+    Ok((ContentType::JSON, runs.lock().get(id)?))
+}
+#[get("/runs?<session>")]
+fn list_runs(
+    runs: &State<Arc<Mutex<RunStore>>>,
+    session: Option<&str>,
+) -> Result<Json<Vec<serde_json::Value>>, ServerError> {
+    match runs.lock().list(session) {
+        Ok(list) => Ok(Json(list)),
+        Err(e) => Err(e),
+    }
+}
+#[get("/session/export")]
+fn export_session(
+    state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+) -> Result<Json<SessionBundle>, ServerError> {
+    if let Solver::None = *state.lock() {
+        return Err(ServerError::InternalError(
+            "Solver::export failed! No control object.".to_string(),
+        ));
+    }
+    Ok(Json(log.lock().session()?))
+}
+#[post("/session/import", format = "application/json", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
+async fn import_session(
+    state: &State<Arc<Mutex<Solver>>>,
+    record: &State<Arc<Mutex<GroundProgramRecord>>>,
+    workspace: &State<Arc<Mutex<Workspace>>>,
+    registry: &State<Arc<TheoryRegistry>>,
+    log: &State<Mutex<CommandLog>>,
+    runs: &State<Arc<Mutex<RunStore>>>,
+    sessions: &State<Arc<Sessions>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let bundle: SessionBundle = serde_json::from_str(&body)
+        .map_err(|e| ServerError::InternalError(format!("Could not parse session bundle {}", e)))?;
 
-    state.lock().add("base", &[], &cap.into_inner())?;
+    let (arguments, commands) = match bundle.commands.split_first() {
+        Some((Command::Create { arguments }, commands)) => (arguments.clone(), commands),
+        _ => {
+            return Err(ServerError::InternalError(
+                "Could not import session! The first command has to create the solver.".to_string(),
+            ))
+        }
+    };
+    // programs are imported by text, files of the server are not read
+    if let Some(command) = commands
+        .iter()
+        .find(|command| !command.is_state() || matches!(command.program(), Some(Program::File(_))))
+    {
+        return Err(ServerError::InternalError(format!(
+            "Could not import session! Unexpected command {:?}.",
+            command
+        )));
+    }
+    sessions.acquire_interactive()?;
+    create_solver(state, record, workspace, log, runs, arguments)?;
+    // an exported session only has successful commands, so the import stops at the first error
+    for (i, command) in commands.iter().enumerate() {
+        let mut solver = state.lock();
+        solver
+            .execute(command, registry, &mut workspace.lock())
+            .map_err(|e| {
+                ServerError::InternalError(format!(
+                    "Could not import session! Command {} failed. {}",
+                    i + 2,
+                    e.message()
+                ))
+            })?;
+        log.lock().record(command.clone())?;
+    }
+    Ok(format!(
+        "Imported session with {} commands.",
+        bundle.commands.len()
+    ))
+}
+#[derive(FromForm)]
+struct ProgramFiles<'r> {
+    files: Vec<TempFile<'r>>,
+    /// names of the files to add, the files not included by other files if not given
+    main: Vec<String>,
+}
+#[post("/add", format = "multipart/form-data", data = "<form>")]
+async fn add_files(
+    state: &State<Arc<Mutex<Solver>>>,
+    workspace: &State<Arc<Mutex<Workspace>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    form: Result<Form<ProgramFiles<'_>>, Errors<'_>>,
+) -> Result<String, ServerError> {
+    let mut form = form.map_err(|e| {
+        if e.status() == Status::PayloadTooLarge {
+            too_large(config.max_body)
+        } else {
+            ServerError::InternalError(format!("Could not parse program files! {}", e))
+        }
+    })?;
+    let dir = workspace.lock().dir()?;
+    let mut names = vec![];
+    let mut programs = vec![];
+    for file in form.files.iter_mut() {
+        let name = workspace_file_name(file)?;
+        let path = dir.join(&name);
+        // an earlier file of the same name is replaced, the session record keeps linking to it
+        let copy = NamedTempFile::new_in(&dir)?.into_temp_path();
+        file.copy_to(&copy).await?;
+        copy.persist(&path).map_err(|e| e.error)?;
+        programs.push(fs::read_to_string(&path)?);
+        names.push(name);
+    }
+    let entries = upload::entry_files(&names, &programs, &form.main)?;
+    add_workspace_files(state, log, &dir, &names, &entries)?;
+    let added: Vec<&str> = entries.iter().map(|&i| names[i].as_str()).collect();
+    Ok(format!("Added {} to Solver.", added.join(", ")))
+}
+/// Add the entry files stored in the workspace `dir` and log all stored files
+///
+/// Included files are only stored, they are logged first so that the replay can resolve them.
+fn add_workspace_files(
+    state: &Mutex<Solver>,
+    log: &Mutex<CommandLog>,
+    dir: &Path,
+    names: &[String],
+    entries: &[usize],
+) -> Result<(), ServerError> {
+    // includes are resolved relative to the files in the workspace
+    let mut solver = state.lock();
+    for (i, name) in names.iter().enumerate() {
+        if !entries.contains(&i) {
+            log.lock().record(Command::AddFile {
+                name: name.clone(),
+                program: Program::File(dir.join(name)),
+                entry: false,
+            })?;
+        }
+    }
+    for &i in entries {
+        let path = dir.join(&names[i]);
+        solver.add_file(&path)?;
+        log.lock().record(Command::AddFile {
+            name: names[i].clone(),
+            program: Program::File(path),
+            entry: true,
+        })?;
+    }
+    Ok(())
+}
+#[post("/add", data = "<data>", rank = 2)]
+async fn add(
+    state: &State<Arc<Mutex<Solver>>>,
+    workspace: &State<Arc<Mutex<Workspace>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    upload: UploadHeaders,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    // stream the program into the workspace instead of buffering it,
+    // there includes of uploaded files are resolved and the log refers to the file
+    let dir = workspace.lock().dir()?;
+    let file = tempfile::Builder::new()
+        .prefix("add-")
+        .suffix(".lp")
+        .tempfile_in(&dir)?;
+    let written = data.open(config.max_body).into_file(file.path()).await?;
+    if !written.is_complete() {
+        return Err(too_large(config.max_body));
+    }
+    if upload.is_tar() {
+        // the members of an archive are stored in the workspace like uploaded files
+        let files = upload::unpack(file.path(), &upload, config.max_body, &dir)?;
+        let mut names = vec![];
+        let mut programs = vec![];
+        for file in &files {
+            let name = file.strip_prefix(&dir).unwrap_or(file);
+            names.push(name.to_string_lossy().into_owned());
+            programs.push(fs::read_to_string(file)?);
+        }
+        let entries = upload::entry_files(&names, &programs, &[])?;
+        add_workspace_files(state, log, &dir, &names, &entries)?;
+        return Ok("Added data to Solver.".to_string());
+    }
+    let files = upload::unpack(file.path(), &upload, config.max_body, &dir)?;
+    let mut solver = state.lock();
+    for file in &files {
+        solver.add_file(file)?;
+    }
+    if upload.is_plain() {
+        // the uploaded file is kept in the workspace for the log
+        file.keep().map_err(|e| e.error)?;
+    }
+    for file in &files {
+        let name = file.strip_prefix(&dir).unwrap_or(file);
+        log.lock().record(Command::AddFile {
+            name: name.to_string_lossy().into_owned(),
+            program: Program::File(file.clone()),
+            entry: true,
+        })?;
+    }
     Ok("Added data to Solver.".to_string())
 }
+#[post("/add", format = "application/json", data = "<data>")]
+async fn add_program_ref(
+    state: &State<Arc<Mutex<Solver>>>,
+    library: &State<Mutex<ProgramLibrary>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
+        .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
+
+    let reference = json_to_program_ref(&val)?;
+    let (version, path) = library.lock().resolve(&reference)?;
+    let mut solver = state.lock();
+    solver.add_file(&path)?;
+    log.lock().record(Command::AddProgramRef {
+        reference: version.reference(),
+        program: Program::File(path),
+    })?;
+    Ok(format!(
+        "Added program {} with sha256 {} to Solver.",
+        version.reference(),
+        version.sha256
+    ))
+}
+#[put("/programs/<name>", data = "<data>")]
+async fn store_program(
+    library: &State<Mutex<ProgramLibrary>>,
+    name: &str,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<Json<ProgramVersion>, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+
+    let version = library.lock().store(name, &body)?;
+    Ok(Json(version))
+}
+#[get("/programs/<name>")]
+fn program_versions(
+    library: &State<Mutex<ProgramLibrary>>,
+    name: &str,
+) -> Result<Json<Vec<ProgramVersion>>, ServerError> {
+    match library.lock().versions(name) {
+        Ok(versions) => Ok(Json(versions)),
+        Err(e) => Err(e),
+    }
+}
+#[post("/facts", format = "application/json", data = "<data>")]
+async fn facts(
+    state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
+        .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
+
+    let facts = json_to_facts(&val)?;
+    let mut solver = state.lock();
+    solver.add_facts(&facts)?;
+    log.lock().record(Command::Facts { facts: val })?;
+    Ok("Added facts to Solver.".to_string())
+}
+#[post("/add_ground_program", format = "application/json", data = "<data>")]
+async fn add_ground_program(
+    state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
+        .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
+
+    let program = json_to_ground_program(&val)?;
+    let mut solver = state.lock();
+    solver.add_ground_program(&program)?;
+    log.lock().record(Command::AddGroundProgram { program })?;
+    Ok("Added ground program to Solver.".to_string())
+}
+#[post("/aspif", data = "<data>")]
+async fn aspif(
+    state: &State<Arc<Mutex<Solver>>>,
+    workspace: &State<Arc<Mutex<Workspace>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    // the data is kept in the workspace for the log
+    let file = tempfile::Builder::new()
+        .prefix("aspif-")
+        .suffix(".aspif")
+        .tempfile_in(workspace.lock().dir()?)?;
+    let written = data.open(config.max_body).into_file(file.path()).await?;
+    if !written.is_complete() {
+        return Err(too_large(config.max_body));
+    }
+    let mut header = [0; 6];
+    let complete = File::open(file.path())?.read_exact(&mut header).is_ok();
+    if !complete || &header != b"asp 1 " {
+        return Err(ServerError::InternalError(
+            "Could not load aspif data! Missing header asp 1 0 0.".to_string(),
+        ));
+    }
+    let (_, path) = file.keep().map_err(|e| e.error)?;
+    let mut solver = state.lock();
+    solver.load_aspif(&path)?;
+    log.lock().record(Command::Aspif {
+        program: Program::File(path),
+    })?;
+    Ok("Added aspif program to Solver.".to_string())
+}
 #[post("/ground", format = "application/json", data = "<data>")]
-async fn ground(state: &State<Arc<Mutex<Solver>>>, data: Data<'_>) -> Result<String, ServerError> {
-    let ds = data.open(512.kibibytes());
-    let cap = ds.into_string().await?;
-    let val = serde_json::from_str(&cap.into_inner())
+async fn ground(
+    state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
         .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
 
-    let parts = json_to_parts(&val)?;
+    let (parts, constants) = json_to_ground_request(&val)?;
     // ground the parts
     let mut solver = state.lock();
-    solver.ground(&parts)?;
+    solver.ground(&parts, &constants)?;
+    log.lock().record(Command::Ground { request: val })?;
     Ok("Grounding.".to_string())
 }
+#[get("/ground_program?<format>")]
+fn export_ground_program(
+    record: &State<Arc<Mutex<GroundProgramRecord>>>,
+    format: Option<&str>,
+) -> Result<(ContentType, String), ServerError> {
+    let record = record.lock();
+    match format.unwrap_or("text") {
+        "aspif" => Ok((ContentType::Plain, record.program.to_aspif())),
+        "text" => Ok((ContentType::Plain, record.program.to_text())),
+        "json" => {
+            let json = serde_json::to_string(&record.program).map_err(|e| {
+                ServerError::InternalError(format!("Could not serialize ground program {}", e))
+            })?;
+            Ok((ContentType::JSON, json))
+        }
+        f => Err(ServerError::InternalError(format!(
+            "Unknown ground program format {}! Expected aspif, text or json.",
+            f
+        ))),
+    }
+}
 #[post("/assign_external", format = "application/json", data = "<data>")]
 async fn assign_external(
     state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
     data: Data<'_>,
 ) -> Result<String, ServerError> {
-    let ds = data.open(512.kibibytes());
-    let cap = ds.into_string().await?;
-    let val = serde_json::from_str(&cap.into_inner())
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
         .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
 
     let assignment = json_to_assignment(&val)?;
     let mut solver = state.lock();
     solver.assign_external(&assignment)?;
+    log.lock()
+        .record(Command::AssignExternal { assignment: val })?;
     Ok("External assigned.".to_string())
 }
 #[post("/release_external", format = "application/json", data = "<data>")]
 async fn release_external(
     state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
     data: Data<'_>,
 ) -> Result<String, ServerError> {
-    let ds = data.open(512.kibibytes());
-    let cap = ds.into_string().await?;
-    let val = serde_json::from_str(&cap.into_inner())
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
         .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
 
     let symbol = json_to_symbol(&val)?;
     let mut solver = state.lock();
     solver.release_external(&symbol)?;
+    log.lock()
+        .record(Command::ReleaseExternal { symbol: val })?;
     Ok("External released.".to_string())
 }
 #[get("/solve")]
-fn solve(state: &State<Arc<Mutex<Solver>>>) -> Result<String, ServerError> {
+fn solve(
+    state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    runs: &State<Arc<Mutex<RunStore>>>,
+    config: &State<ServerConfig>,
+) -> Result<String, ServerError> {
     let mut solver = state.lock();
-    solver.solve(SolveMode::ASYNC | SolveMode::YIELD, &[])?;
+    let mut log = log.lock();
+    // the run is started first, the search records its models and result
+    runs.lock().start(&log, None)?;
+    if let Err(e) = solver.solve(SolveMode::ASYNC | SolveMode::YIELD, &[]) {
+        runs.lock().abort()?;
+        return Err(e);
+    }
+    watch_solve_timeout(state, config.solve_timeout, solver.search());
+    log.record(Command::Solve)?;
     Ok("Solving.".to_string())
 }
 #[post(
@@ -94,105 +542,465 @@ fn solve(state: &State<Arc<Mutex<Solver>>>) -> Result<String, ServerError> {
 )]
 async fn solve_with_assumptions(
     state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    runs: &State<Arc<Mutex<RunStore>>>,
+    config: &State<ServerConfig>,
     data: Data<'_>,
 ) -> Result<String, ServerError> {
-    let ds = data.open(512.kibibytes());
-    let cap = ds.into_string().await?;
-    let val = serde_json::from_str(&cap.into_inner())
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
         .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
 
     let assumptions = json_to_assumptions(&val)?;
     let mut solver = state.lock();
-    solver.solve_with_assumptions(&assumptions)?;
+    let mut log = log.lock();
+    runs.lock().start(&log, Some(val.clone()))?;
+    if let Err(e) = solver.solve_with_assumptions(&assumptions) {
+        runs.lock().abort()?;
+        return Err(e);
+    }
+    watch_solve_timeout(state, config.solve_timeout, solver.search());
+    log.record(Command::SolveWithAssumptions { assumptions: val })?;
     Ok("Solving with assumptions.".to_string())
 }
+/// Cancel the search once it runs longer than the solve timeout, even if no model is requested
+fn watch_solve_timeout(state: &Arc<Mutex<Solver>>, timeout: Option<u64>, search: Option<u64>) {
+    if let (Some(timeout), Some(search)) = (timeout, search) {
+        let state = state.clone();
+        let timeout = Duration::from_secs(timeout);
+        thread::spawn(move || {
+            thread::sleep(timeout);
+            let mut solver = state.lock();
+            // the search may have been closed or replaced in the meantime
+            if solver.search() == Some(search) {
+                solver.check_timeout(timeout).ok();
+            }
+        });
+    }
+}
 #[get("/model")]
-fn model(state: &State<Arc<Mutex<Solver>>>) -> Result<Json<ModelResult>, ServerError> {
+fn model(
+    state: &State<Arc<Mutex<Solver>>>,
+    runs: &State<Arc<Mutex<RunStore>>>,
+    config: &State<ServerConfig>,
+) -> Result<Json<ModelResult>, ServerError> {
     let mut solver = state.lock();
-    match solver.model() {
-        Ok(mr) => Ok(Json(mr)),
-        Err(e) => Err(e),
+    if let Some(timeout) = config.solve_timeout {
+        solver.check_timeout(Duration::from_secs(timeout))?;
+    }
+    let mr = solver.model()?;
+    let mut runs = runs.lock();
+    if let ModelResult::Done = mr {
+        if let Ok(statistics) = solver.statistics() {
+            runs.set_statistics(statistics)?;
+        }
     }
+    // errors of recording the models are reported to the client
+    runs.check()?;
+    Ok(Json(mr))
 }
 #[get("/resume")]
-fn resume(state: &State<Arc<Mutex<Solver>>>) -> Result<String, ServerError> {
+fn resume(
+    state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+) -> Result<String, ServerError> {
     let mut solver = state.lock();
     solver.resume()?;
+    log.lock().record(Command::Resume)?;
     Ok("Search is resumed.".to_string())
 }
 #[get("/close")]
-fn close(state: &State<Arc<Mutex<Solver>>>) -> Result<String, ServerError> {
+fn close(
+    state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    runs: &State<Arc<Mutex<RunStore>>>,
+) -> Result<String, ServerError> {
     let mut solver = state.lock();
     solver.close()?;
+    log.lock().record(Command::Close)?;
+    let statistics = solver.statistics().ok();
+    runs.lock().close(statistics)?;
     Ok("Solve handle closed.".to_string())
 }
 #[get("/register_dl_theory")]
-fn register_dl_theory(state: &State<Arc<Mutex<Solver>>>) -> Result<String, ServerError> {
+fn register_dl_theory(
+    state: &State<Arc<Mutex<Solver>>>,
+    registry: &State<Arc<TheoryRegistry>>,
+    log: &State<Mutex<CommandLog>>,
+) -> Result<String, ServerError> {
     let mut solver = state.lock();
-    solver.register_dl_theory()?;
+    solver.register_theory("dl", registry.create("dl")?)?;
+    log.lock().record(Command::RegisterTheory {
+        name: "dl".to_string(),
+        options: vec![],
+    })?;
     Ok("Difference logic theory registered.".to_string())
 }
 #[get("/register_con_theory")]
-fn register_con_theory(state: &State<Arc<Mutex<Solver>>>) -> Result<String, ServerError> {
+fn register_con_theory(
+    state: &State<Arc<Mutex<Solver>>>,
+    registry: &State<Arc<TheoryRegistry>>,
+    log: &State<Mutex<CommandLog>>,
+) -> Result<String, ServerError> {
     let mut solver = state.lock();
-    solver.register_con_theory()?;
+    solver.register_theory("con", registry.create("con")?)?;
+    log.lock().record(Command::RegisterTheory {
+        name: "con".to_string(),
+        options: vec![],
+    })?;
     Ok("Clingcon theory registered.".to_string())
 }
-#[get("/statistics")]
-fn statistics(state: &State<Arc<Mutex<Solver>>>) -> Result<Json<StatisticsResult>, ServerError> {
+#[post("/register_dl_theory", format = "application/json", data = "<data>")]
+async fn register_dl_theory_with_options(
+    state: &State<Arc<Mutex<Solver>>>,
+    registry: &State<Arc<TheoryRegistry>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let (theory, options) = configured_theory(registry, "dl", data, config.max_body).await?;
+    let mut solver = state.lock();
+    solver.register_theory("dl", theory)?;
+    log.lock().record(Command::RegisterTheory {
+        name: "dl".to_string(),
+        options,
+    })?;
+    Ok("Difference logic theory registered.".to_string())
+}
+#[post("/register_con_theory", format = "application/json", data = "<data>")]
+async fn register_con_theory_with_options(
+    state: &State<Arc<Mutex<Solver>>>,
+    registry: &State<Arc<TheoryRegistry>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let (theory, options) = configured_theory(registry, "con", data, config.max_body).await?;
+    let mut solver = state.lock();
+    solver.register_theory("con", theory)?;
+    log.lock().record(Command::RegisterTheory {
+        name: "con".to_string(),
+        options,
+    })?;
+    Ok("Clingcon theory registered.".to_string())
+}
+#[post("/register_theory/<name>", data = "<data>")]
+async fn register_theory(
+    state: &State<Arc<Mutex<Solver>>>,
+    registry: &State<Arc<TheoryRegistry>>,
+    log: &State<Mutex<CommandLog>>,
+    name: &str,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let (theory, options) = configured_theory(registry, name, data, config.max_body).await?;
+    let mut solver = state.lock();
+    solver.register_theory(name, theory)?;
+    log.lock().record(Command::RegisterTheory {
+        name: name.to_string(),
+        options,
+    })?;
+    Ok(format!("Theory {} registered.", name))
+}
+/// Create the theory and configure it with the options in the request body, an empty body means no options
+async fn configured_theory(
+    registry: &TheoryRegistry,
+    name: &str,
+    data: Data<'_>,
+    limit: ByteUnit,
+) -> Result<(TheoryInstance, Vec<(String, String)>), ServerError> {
+    let body = read_body(data, limit).await?;
+    let options = if body.trim().is_empty() {
+        vec![]
+    } else {
+        let val = serde_json::from_str(&body)
+            .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
+        json_to_theory_options(&val)?
+    };
+    let mut theory = registry.create(name)?;
+    theory.configure(name, &options)?;
+    Ok((theory, options))
+}
+#[post("/jobs", format = "application/json", data = "<data>")]
+async fn submit_job(
+    jobs: &State<JobQueue>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<Json<Job>, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let request: JobRequest = serde_json::from_str(&body)
+        .map_err(|e| ServerError::InternalError(format!("Could not parse job {}", e)))?;
+
+    match jobs.submit(&request) {
+        Ok(job) => Ok(Json(job)),
+        Err(e) => Err(e),
+    }
+}
+#[get("/jobs/<id>")]
+fn job(jobs: &State<JobQueue>, id: u64) -> Result<Json<Job>, ServerError> {
+    match jobs.get(id) {
+        Ok(job) => Ok(Json(job)),
+        Err(e) => Err(e),
+    }
+}
+#[get("/plugins")]
+fn plugins(plugins: &State<Vec<PluginInfo>>) -> Json<Vec<PluginInfo>> {
+    Json(plugins.inner().clone())
+}
+#[get("/statistics?<path>")]
+fn statistics(
+    state: &State<Arc<Mutex<Solver>>>,
+    path: Option<String>,
+) -> Result<Json<StatisticsResult>, ServerError> {
+    let mut solver = state.lock();
+    let stats = match path {
+        Some(path) => match solver.statistics_at(&[path])? {
+            StatisticsResult::Map(mut array) => array.remove(0).1,
+            stats => stats,
+        },
+        None => solver.statistics()?,
+    };
+    Ok(Json(stats))
+}
+#[post("/statistics", format = "application/json", data = "<data>")]
+async fn statistics_at(
+    state: &State<Arc<Mutex<Solver>>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<Json<StatisticsResult>, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
+        .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
+
+    let paths = json_to_statistics_paths(&val)?;
     let mut solver = state.lock();
-    match solver.statistics() {
+    match solver.statistics_at(&paths) {
         Ok(stats) => Ok(Json(stats)),
         Err(e) => Err(e),
     }
 }
-#[get("/configuration")]
-fn configuration(
+#[post("/user_statistics", format = "application/json", data = "<data>")]
+async fn user_statistics(
+    state: &State<Arc<Mutex<Solver>>>,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
+        .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
+
+    let entries = json_to_user_statistics(&val)?;
+    let mut solver = state.lock();
+    solver.add_user_statistics(entries)?;
+    Ok("Added user statistics.".to_string())
+}
+#[get("/statistics/theory?<view>")]
+fn theory_statistics(
     state: &State<Arc<Mutex<Solver>>>,
-) -> Result<Json<ConfigurationResult>, ServerError> {
+    view: Option<&str>,
+) -> Result<Json<StatisticsResult>, ServerError> {
+    let view = StatisticsView::parse(view.unwrap_or("both"))?;
     let mut solver = state.lock();
-    match solver.configuration() {
+    match solver.theory_statistics(view) {
         Ok(stats) => Ok(Json(stats)),
         Err(e) => Err(e),
     }
 }
+#[get("/theory_atoms")]
+fn theory_atoms(
+    state: &State<Arc<Mutex<Solver>>>,
+) -> Result<Json<Vec<TheoryAtomResult>>, ServerError> {
+    let mut solver = state.lock();
+    match solver.theory_atoms() {
+        Ok(atoms) => Ok(Json(atoms)),
+        Err(e) => Err(e),
+    }
+}
+#[get("/configuration?<verbose>")]
+fn configuration(
+    state: &State<Arc<Mutex<Solver>>>,
+    verbose: Option<bool>,
+) -> Result<Json<ConfigurationListing>, ServerError> {
+    let mut solver = state.lock();
+    match solver.configuration(None, verbose.unwrap_or(false)) {
+        Ok(conf) => Ok(Json(conf)),
+        Err(e) => Err(e),
+    }
+}
+#[get("/configuration/<path>?<verbose>")]
+fn configuration_at(
+    state: &State<Arc<Mutex<Solver>>>,
+    path: &str,
+    verbose: Option<bool>,
+) -> Result<Json<ConfigurationListing>, ServerError> {
+    let mut solver = state.lock();
+    match solver.configuration(Some(path), verbose.unwrap_or(false)) {
+        Ok(conf) => Ok(Json(conf)),
+        Err(e) => Err(e),
+    }
+}
+#[put("/configuration/<path>", data = "<data>")]
+async fn set_configuration_value(
+    state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    path: &str,
+    config: &State<ServerConfig>,
+    data: Data<'_>,
+) -> Result<String, ServerError> {
+    let body = read_body(data, config.max_body).await?;
+
+    let mut solver = state.lock();
+    solver.set_configuration_value(path, body.trim())?;
+    log.lock().record(Command::SetConfigurationValue {
+        path: path.to_string(),
+        value: body.trim().to_string(),
+    })?;
+    Ok(format!("Set configuration {}.", path))
+}
 #[post("/set_configuration", format = "application/json", data = "<data>")]
 async fn set_configuration(
     state: &State<Arc<Mutex<Solver>>>,
+    log: &State<Mutex<CommandLog>>,
+    config: &State<ServerConfig>,
     data: Data<'_>,
 ) -> Result<String, ServerError> {
-    let ds = data.open(512.kibibytes());
-    let cap = ds.into_string().await?;
-    let val = serde_json::from_str(&cap.into_inner())
+    let body = read_body(data, config.max_body).await?;
+    let val = serde_json::from_str(&body)
         .map_err(|e| ServerError::InternalError(format!("Could not parse json data {}", e)))?;
 
     let c = json_to_configuration_result(&val)?;
     let mut solver = state.lock();
     solver.set_configuration(&c)?;
+    log.lock()
+        .record(Command::SetConfiguration { configuration: val })?;
     Ok("Set configuration.".to_string())
 }
-#[launch]
-fn rocket() -> _ {
+#[rocket::main]
+async fn main() {
+    let cli = Cli::parse();
+    match &cli.command {
+        Some(CliCommand::Replay { file }) => {
+            let config: ServerConfig = cli
+                .figment()
+                .extract()
+                .unwrap_or_else(|e| exit(format!("Invalid server configuration! {}", e)));
+            if let Err(e) = config.validate() {
+                exit(e);
+            }
+            let mut registry = TheoryRegistry::default();
+            plugin::load_plugins(&config.plugins, &mut registry);
+            if let Err(e) = command::replay(file, &registry, &mut std::io::stdout()) {
+                exit(e.message());
+            }
+        }
+        None => {
+            if let Err(e) = build(cli.figment()).launch().await {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+/// Print the error of a server that can not start and exit
+fn exit(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+/// The server with the default configuration
+#[cfg(test)]
+fn rocket() -> Rocket<Build> {
+    build(rocket::Config::figment())
+}
+fn build(figment: Figment) -> Rocket<Build> {
+    let config: ServerConfig = figment
+        .extract()
+        .unwrap_or_else(|e| exit(format!("Invalid server configuration! {}", e)));
+    if let Err(e) = config.validate() {
+        exit(e);
+    }
+    let mut registry = TheoryRegistry::default();
+    let plugins = plugin::load_plugins(&config.plugins, &mut registry);
+    let library = ProgramLibrary::open(config.library_dir.as_deref())
+        .unwrap_or_else(|e| exit(format!("Could not open program library! {:?}", e)));
+    let log = CommandLog::open(config.command_log.as_deref())
+        .unwrap_or_else(|e| exit(format!("Could not open command log! {}", e)));
+    let runs = RunStore::open(config.run_dir.as_deref())
+        .unwrap_or_else(|e| exit(format!("Could not open result store! {:?}", e)));
+    let registry = Arc::new(registry);
+    let sessions = Arc::new(Sessions::new(config.max_sessions));
+    let jobs = JobQueue::start(
+        config.job_workers,
+        config.job_queue,
+        config.job_retention,
+        registry.clone(),
+        sessions.clone(),
+        config.solve_timeout.map(Duration::from_secs),
+        config.default_arguments.clone(),
+    );
     let state: Arc<Mutex<Solver>> = Arc::new(Mutex::new(Solver::None));
-    rocket::build().manage(state).mount(
-        "/",
-        routes![
-            index,
-            create,
-            add,
-            ground,
-            assign_external,
-            release_external,
-            solve,
-            model,
-            resume,
-            close,
-            statistics,
-            configuration,
-            set_configuration,
-            solve_with_assumptions,
-            register_dl_theory,
-            register_con_theory
-        ],
-    )
+    let record: Arc<Mutex<GroundProgramRecord>> = Arc::new(Mutex::new(Default::default()));
+    let workspace: Arc<Mutex<Workspace>> = Arc::new(Mutex::new(Default::default()));
+    // multipart uploads are subject to the same limit as other bodies
+    let figment = figment
+        .merge(("limits.data-form", config.max_body))
+        .merge(("limits.file", config.max_body));
+    rocket::custom(figment)
+        .manage(state)
+        .manage(record)
+        .manage(workspace)
+        .manage(jobs)
+        .manage(registry)
+        .manage(Mutex::new(library))
+        .manage(Mutex::new(log))
+        .manage(Arc::new(Mutex::new(runs)))
+        .manage(plugins)
+        .manage(sessions)
+        .manage(config)
+        .mount(
+            "/",
+            routes![
+                index,
+                create,
+                create_with_arguments,
+                add,
+                add_files,
+                current_session,
+                run,
+                list_runs,
+                export_session,
+                import_session,
+                add_program_ref,
+                store_program,
+                program_versions,
+                facts,
+                add_ground_program,
+                aspif,
+                ground,
+                export_ground_program,
+                assign_external,
+                release_external,
+                solve,
+                model,
+                resume,
+                close,
+                statistics,
+                statistics_at,
+                user_statistics,
+                theory_statistics,
+                theory_atoms,
+                configuration,
+                configuration_at,
+                set_configuration_value,
+                set_configuration,
+                solve_with_assumptions,
+                register_dl_theory,
+                register_con_theory,
+                register_dl_theory_with_options,
+                register_con_theory_with_options,
+                register_theory,
+                plugins,
+                submit_job,
+                job
+            ],
+        )
 }
@@ -0,0 +1,131 @@
+use crate::theory::{CTheory, TheoryApi, TheoryRegistry};
+use libloading::Library;
+use std::os::raw::c_int;
+use std::sync::Arc;
+
+/// Signature of the symbol `<prefix>_version` like `clingodl_version`
+pub type TheoryVersion =
+    unsafe extern "C" fn(major: *mut c_int, minor: *mut c_int, patch: *mut c_int);
+
+/// A theory plugin as given in the server configuration
+///
+/// The plugin is a shared library with the C interface of clingo-dl and clingcon,
+/// i.e. the functions `<prefix>_create`, `<prefix>_register`, ... and `<prefix>_version`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginConfig {
+    pub name: String,
+    pub path: String,
+    /// prefix of the exported functions, the name of the plugin if not set
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub statistics: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub path: String,
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+struct LoadedPlugin {
+    library: Arc<Library>,
+    api: TheoryApi,
+    version: String,
+}
+
+fn load(config: &PluginConfig) -> Result<LoadedPlugin, String> {
+    let library = unsafe { Library::new(&config.path) }.map_err(|e| {
+        format!(
+            "Could not load plugin {} from {}! {}",
+            config.name, config.path, e
+        )
+    })?;
+    let prefix = config.prefix.as_deref().unwrap_or(&config.name);
+    // the function types are the ones of the C interface, the library stays loaded with the api
+    fn get<T: Copy>(
+        library: &Library,
+        config: &PluginConfig,
+        prefix: &str,
+        name: &str,
+    ) -> Result<T, String> {
+        let symbol = format!("{}_{}", prefix, name);
+        unsafe { library.get::<T>(symbol.as_bytes()) }
+            .map(|symbol| *symbol)
+            .map_err(|e| {
+                format!(
+                    "Plugin {} in {} has no symbol {}! {}",
+                    config.name, config.path, symbol, e
+                )
+            })
+    }
+    let api = TheoryApi {
+        create: get(&library, config, prefix, "create")?,
+        destroy: get(&library, config, prefix, "destroy")?,
+        configure: get(&library, config, prefix, "configure")?,
+        register: get(&library, config, prefix, "register")?,
+        rewrite_ast: get(&library, config, prefix, "rewrite_ast")?,
+        prepare: get(&library, config, prefix, "prepare")?,
+        on_model: get(&library, config, prefix, "on_model")?,
+        on_statistics: get(&library, config, prefix, "on_statistics")?,
+        get_symbol: get(&library, config, prefix, "get_symbol")?,
+        assignment_begin: get(&library, config, prefix, "assignment_begin")?,
+        assignment_next: get(&library, config, prefix, "assignment_next")?,
+        assignment_has_value: get(&library, config, prefix, "assignment_has_value")?,
+        assignment_get_value: get(&library, config, prefix, "assignment_get_value")?,
+    };
+    let version: TheoryVersion = get(&library, config, prefix, "version")?;
+    let (mut major, mut minor, mut patch) = (0, 0, 0);
+    unsafe { version(&mut major, &mut minor, &mut patch) };
+    Ok(LoadedPlugin {
+        library: Arc::new(library),
+        api,
+        version: format!("{}.{}.{}", major, minor, patch),
+    })
+}
+
+/// Load the configured plugins and add them to the registry
+///
+/// Plugins that fail to load are listed with their error and can not be registered.
+/// A plugin can not replace a theory of the same name, e.g. the built-in theories `dl` and `con`.
+pub fn load_plugins(configs: &[PluginConfig], registry: &mut TheoryRegistry) -> Vec<PluginInfo> {
+    configs
+        .iter()
+        .map(|config| {
+            let mut info = PluginInfo {
+                name: config.name.clone(),
+                path: config.path.clone(),
+                version: None,
+                error: None,
+            };
+            if registry.contains(&config.name) {
+                info.error = Some(format!(
+                    "Plugin {} in {} is not loaded! A theory named {} already exists.",
+                    config.name, config.path, config.name
+                ));
+                return info;
+            }
+            match load(config) {
+                Ok(LoadedPlugin {
+                    library,
+                    api,
+                    version,
+                }) => {
+                    let statistics = config.statistics.as_deref();
+                    // every theory keeps the library loaded until it is destroyed
+                    registry.insert(&config.name, statistics, move || {
+                        Ok(Box::new(CTheory::create(api, Some(library.clone()))?))
+                    });
+                    info.version = Some(version);
+                }
+                Err(e) => {
+                    registry.insert_unavailable(&config.name, &e);
+                    info.error = Some(e);
+                }
+            }
+            info
+        })
+        .collect()
+}
@@ -0,0 +1,306 @@
+use crate::command::{Command, CommandLog};
+use crate::utils::{ServerError, SolveSummary, StatisticsResult};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static SESSIONS: AtomicU64 = AtomicU64::new(0);
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+/// A new session id, unique across restarts of the server
+fn session_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    format!("{:x}{:x}", millis, SESSIONS.fetch_add(1, Ordering::SeqCst))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunModel {
+    pub model: String,
+    pub costs: Vec<i64>,
+}
+
+/// A solve run with its results
+///
+/// The models are stored apart in `<id>.models.jsonl`
+/// and the inputs once per session in `<session>.inputs.jsonl`.
+#[derive(Debug, Serialize)]
+pub struct RunRecord {
+    pub id: String,
+    pub session: String,
+    /// Seconds since the unix epoch
+    pub started: u64,
+    pub finished: Option<u64>,
+    /// The number of commands of the session that built the control object
+    pub inputs: usize,
+    pub assumptions: Option<Value>,
+    pub statistics: Option<StatisticsResult>,
+    pub result: Option<SolveSummary>,
+}
+
+/// Read the json values of a JSONL file, a missing file has no values
+fn read_lines(path: &Path) -> Result<Vec<Value>, ServerError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    BufReader::new(File::open(path)?)
+        .lines()
+        .map(|line| {
+            serde_json::from_str(&line?).map_err(|e| {
+                ServerError::InternalError(format!("Could not parse {}! {}", path.display(), e))
+            })
+        })
+        .collect()
+}
+
+/// Stores the solve runs in a directory, disabled if no directory is configured
+///
+/// The record `<id>.json` of a run is written when the run starts and when it ends,
+/// the models are appended by the solve event handler while the search runs,
+/// so they are kept when the client disconnects.
+/// The commands of a session are appended to its inputs when a run starts,
+/// their program files are stored in `files` named by their sha256.
+pub struct RunStore {
+    dir: Option<PathBuf>,
+    session: String,
+    runs: u64,
+    /// The number of commands of the session stored in its inputs
+    inputs: usize,
+    current: Option<RunRecord>,
+    models: Option<File>,
+    /// The first error recording the current run in the solve event handler
+    error: Option<String>,
+}
+impl RunStore {
+    pub fn open(dir: Option<&Path>) -> Result<RunStore, ServerError> {
+        if let Some(dir) = dir {
+            fs::create_dir_all(dir.join("files"))?;
+        }
+        Ok(RunStore {
+            dir: dir.map(Path::to_path_buf),
+            session: session_id(),
+            runs: 0,
+            inputs: 0,
+            current: None,
+            models: None,
+            error: None,
+        })
+    }
+    /// The id of the current session
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+    pub fn new_session(&mut self) {
+        self.session = session_id();
+        self.runs = 0;
+        self.inputs = 0;
+        self.current = None;
+        self.models = None;
+        self.error = None;
+    }
+    /// Start recording a run before the search starts, returns its id
+    ///
+    /// The commands recorded by `log` since the last run are added to the inputs of the session.
+    pub fn start(
+        &mut self,
+        log: &CommandLog,
+        assumptions: Option<Value>,
+    ) -> Result<Option<String>, ServerError> {
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => return Ok(None),
+        };
+        let commands = log.session_commands(self.inputs)?;
+        if !commands.is_empty() {
+            let mut inputs = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join(format!("{}.inputs.jsonl", self.session)))?;
+            for command in &commands {
+                let command = command.map_program(|program| program.stored(&dir.join("files")))?;
+                let line = serde_json::to_string(&command).map_err(|e| {
+                    ServerError::InternalError(format!("Could not serialize command {}", e))
+                })?;
+                writeln!(inputs, "{}", line)?;
+            }
+            self.inputs += commands.len();
+        }
+        self.runs += 1;
+        let id = format!("{}-{}", self.session, self.runs);
+        self.models = Some(File::create(dir.join(format!("{}.models.jsonl", id)))?);
+        self.current = Some(RunRecord {
+            id: id.clone(),
+            session: self.session.clone(),
+            started: now(),
+            finished: None,
+            inputs: self.inputs,
+            assumptions,
+            statistics: None,
+            result: None,
+        });
+        self.error = None;
+        self.write()?;
+        Ok(Some(id))
+    }
+    /// Drop the current run if its search could not be started
+    pub fn abort(&mut self) -> Result<(), ServerError> {
+        if let (Some(dir), Some(run)) = (&self.dir, self.current.take()) {
+            self.models = None;
+            fs::remove_file(dir.join(format!("{}.json", run.id)))?;
+            fs::remove_file(dir.join(format!("{}.models.jsonl", run.id)))?;
+            self.runs -= 1;
+        }
+        Ok(())
+    }
+    /// Record a model found by the search, a failure is reported by [`RunStore::check`]
+    pub fn add_model(&mut self, model: String, costs: Vec<i64>) {
+        if let Some(models) = &mut self.models {
+            let result = serde_json::to_string(&RunModel { model, costs })
+                .map_err(io::Error::from)
+                .and_then(|line| writeln!(models, "{}", line));
+            if let Err(e) = result {
+                self.failed(e.into());
+            }
+        }
+    }
+    /// Record the result of the search, a failure is reported by [`RunStore::check`]
+    pub fn finish(&mut self, result: SolveSummary) {
+        if let Some(run) = &mut self.current {
+            run.finished = Some(now());
+            run.result = Some(result);
+            if let Err(e) = self.write() {
+                self.failed(e);
+            }
+        }
+    }
+    fn failed(&mut self, error: ServerError) {
+        if let (None, Some(run)) = (&self.error, &self.current) {
+            self.error = Some(format!(
+                "Could not record run {}! {}",
+                run.id,
+                error.message()
+            ));
+        }
+    }
+    /// Report the first error of recording the current run in the solve event handler
+    pub fn check(&mut self) -> Result<(), ServerError> {
+        match self.error.take() {
+            Some(error) => Err(ServerError::InternalError(error)),
+            None => Ok(()),
+        }
+    }
+    /// Record the statistics of the finished search
+    pub fn set_statistics(&mut self, statistics: StatisticsResult) -> Result<(), ServerError> {
+        if let Some(run) = &mut self.current {
+            run.statistics = Some(statistics);
+            self.write()?;
+        }
+        Ok(())
+    }
+    /// Close the current run, the statistics of the closed search replace the snapshot
+    pub fn close(&mut self, statistics: Option<StatisticsResult>) -> Result<(), ServerError> {
+        if let Some(run) = &mut self.current {
+            run.finished.get_or_insert_with(now);
+            if statistics.is_some() {
+                run.statistics = statistics;
+            }
+            self.write()?;
+        }
+        self.current = None;
+        self.models = None;
+        self.check()
+    }
+    fn write(&self) -> Result<(), ServerError> {
+        if let (Some(dir), Some(run)) = (&self.dir, &self.current) {
+            let json = serde_json::to_string_pretty(run).map_err(|e| {
+                ServerError::InternalError(format!("Could not serialize run {}! {}", run.id, e))
+            })?;
+            let tmp = dir.join(format!("{}.json.tmp", run.id));
+            fs::write(&tmp, json)?;
+            fs::rename(tmp, dir.join(format!("{}.json", run.id)))?;
+        }
+        Ok(())
+    }
+    fn dir(&self) -> Result<&Path, ServerError> {
+        self.dir.as_deref().ok_or_else(|| {
+            ServerError::InternalError(
+                "The result store is disabled! Set run_dir in the server configuration."
+                    .to_string(),
+            )
+        })
+    }
+    /// The stored record of a run as json with its inputs and models
+    pub fn get(&self, id: &str) -> Result<String, ServerError> {
+        let dir = self.dir()?;
+        let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        let path = dir.join(format!("{}.json", id));
+        if !valid || !path.exists() {
+            return Err(ServerError::InternalError(format!("Unknown run {}!", id)));
+        }
+        let mut run: Value = serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| {
+            ServerError::InternalError(format!("Could not parse run {}! {}", id, e))
+        })?;
+        let session = run["session"].as_str().unwrap_or_default().to_string();
+        let count = run["inputs"].as_u64().unwrap_or(0) as usize;
+        let mut inputs = vec![];
+        for input in read_lines(&dir.join(format!("{}.inputs.jsonl", session)))?
+            .into_iter()
+            .take(count)
+        {
+            let command: Command = serde_json::from_value(input).map_err(|e| {
+                ServerError::InternalError(format!("Could not parse input of run {}! {}", id, e))
+            })?;
+            let command =
+                command.map_program(|program| program.resolved(&dir.join("files"))?.inlined())?;
+            inputs.push(command);
+        }
+        run["inputs"] = serde_json::json!(inputs);
+        run["models"] = Value::Array(read_lines(&dir.join(format!("{}.models.jsonl", id)))?);
+        serde_json::to_string_pretty(&run).map_err(|e| {
+            ServerError::InternalError(format!("Could not serialize run {}! {}", id, e))
+        })
+    }
+    /// The stored runs without their inputs and models, optionally of a single session
+    pub fn list(&self, session: Option<&str>) -> Result<Vec<Value>, ServerError> {
+        let dir = self.dir()?;
+        let mut runs = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+            let run: Value = match serde_json::from_str(&fs::read_to_string(&path)?) {
+                Ok(run) => run,
+                Err(_) => continue,
+            };
+            if session.map_or(true, |session| run["session"] == session) {
+                let models = dir.join(format!(
+                    "{}.models.jsonl",
+                    run["id"].as_str().unwrap_or_default()
+                ));
+                let models = match File::open(models) {
+                    Ok(file) => BufReader::new(file).lines().count(),
+                    Err(_) => 0,
+                };
+                runs.push(serde_json::json!({
+                    "id": run["id"],
+                    "session": run["session"],
+                    "started": run["started"],
+                    "finished": run["finished"],
+                    "models": models,
+                    "result": run["result"],
+                }));
+            }
+        }
+        runs.sort_by_key(|run| run["started"].as_u64());
+        Ok(runs)
+    }
+}
@@ -1,8 +1,11 @@
-use super::rocket;
+use super::{build, command, rocket};
+use crate::config::ServerConfig;
+use crate::theory::TheoryRegistry;
 
 use rocket::http::ContentType;
 use rocket::http::Status;
 use rocket::local::blocking::Client;
+use rocket::Config;
 use serde_json::Value;
 
 #[test]
@@ -83,6 +86,34 @@ fn test_add() {
     let data: Value = serde_json::from_str(&data).unwrap();
     assert_eq!(data["type"], "InternalError");
     assert_eq!(&data["msg"], "Solver::add failed! No control object.");
+
+    // syntax errors are reported by /add, the statements are added to the control object by /ground
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("p(.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert!(data["msg"]
+        .as_str()
+        .unwrap()
+        .starts_with("Solver::add failed!"));
+    let response = client
+        .post("/add")
+        .body("#const n=1. #const n=2. p(n).")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Added data to Solver.".into()));
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert!(data["msg"].is_string());
 }
 #[test]
 fn test_ground() {
@@ -160,3 +191,1774 @@ fn test_statistics() {
         "Solver::statistics failed! No control object."
     );
 }
+#[test]
+fn test_facts() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client
+        .post("/facts")
+        .header(ContentType::JSON)
+        .body("{\"predicate\":\"edge\",\"tuples\":[[1,2],[2,3]]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(&data["msg"], "Solver::add_facts failed! No control object.");
+
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/facts")
+        .header(ContentType::JSON)
+        .body("{\"predicate\":\"edge\",\"tuples\":[[1,2],[2,\"b\"]]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Added facts to Solver.".into())
+    );
+    let response = client
+        .post("/facts")
+        .header(ContentType::JSON)
+        .body("[\"a :- b\"]")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "ClingoError");
+
+    // the facts added before grounding are kept and part of the model
+    let response = client.post("/add").body("node(X) :- edge(X,_).").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut body_string = client.get("/model").dispatch().into_string();
+    while body_string == Some("\"Running\"".into()) {
+        body_string = client.get("/model").dispatch().into_string();
+    }
+    let data: Value = serde_json::from_str(&body_string.unwrap()).unwrap();
+    let model: Vec<u8> = data["Model"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|byte| byte.as_u64().unwrap() as u8)
+        .collect();
+    let model = String::from_utf8(model).unwrap();
+    let atoms: Vec<&str> = model.split_whitespace().collect();
+    assert!(atoms.contains(&"edge(1,2)"));
+    assert!(atoms.contains(&"edge(2,b)"));
+    assert!(atoms.contains(&"node(1)"));
+    assert!(atoms.contains(&"node(2)"));
+}
+#[test]
+fn test_add_ground_program() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/add_ground_program")
+        .header(ContentType::JSON)
+        .body(
+            "{\"atoms\":[{\"id\":1,\"symbol\":\"a\"}],\
+            \"rules\":[{\"head\":[1],\"body\":[-2]}]}",
+        )
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Added ground program to Solver.".into())
+    );
+    // the fact b is not added because a later rule is invalid
+    let response = client
+        .post("/add_ground_program")
+        .header(ContentType::JSON)
+        .body(
+            "{\"atoms\":[{\"id\":1,\"symbol\":\"b\"}],\
+            \"rules\":[{\"head\":[1]},{\"head\":[2],\"body\":[0]}]}",
+        )
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Could not add ground program! 0 is not a valid literal."
+    );
+    // the ids refer to the same atoms for the whole session
+    let response = client
+        .post("/add_ground_program")
+        .header(ContentType::JSON)
+        .body(
+            "{\"atoms\":[{\"id\":3,\"symbol\":\"c\"}],\
+            \"rules\":[{\"head\":[3],\"body\":[1]}]}",
+        )
+        .dispatch();
+    assert_eq!(
+        response.into_string(),
+        Some("Added ground program to Solver.".into())
+    );
+    let response = client
+        .post("/add_ground_program")
+        .header(ContentType::JSON)
+        .body("{\"atoms\":[{\"id\":1,\"symbol\":\"c\"}]}")
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "Could not add ground program! Atom 1 is already defined as another atom."
+    );
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut response = client.get("/model").dispatch();
+    let mut body_string = response.into_string();
+    while body_string == Some("\"Running\"".into()) {
+        response = client.get("/model").dispatch();
+        body_string = response.into_string();
+    }
+    let data = body_string.unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(
+        data["Model"],
+        Value::Array(
+            "a c\n"
+                .bytes()
+                .map(|byte| Value::Number(byte.into()))
+                .collect()
+        )
+    );
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+#[test]
+fn test_aspif() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/aspif").body("a :- not b.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Could not load aspif data! Missing header asp 1 0 0."
+    );
+    // the heuristic statement is passed on to the solver
+    let response = client
+        .post("/aspif")
+        .body("asp 1 0 0\n1 0 1 1 0 1 -2\n7 0 1 1 0 0\n4 1 a 1 1\n0\n")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Added aspif program to Solver.".into())
+    );
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut body_string = client.get("/model").dispatch().into_string();
+    while body_string == Some("\"Running\"".into()) {
+        body_string = client.get("/model").dispatch().into_string();
+    }
+    let data: Value = serde_json::from_str(&body_string.unwrap()).unwrap();
+    assert_eq!(
+        data["Model"],
+        Value::Array(vec![Value::Number(97.into()), Value::Number(10.into())])
+    );
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+#[test]
+fn test_ground_program() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("{a;b}. c :- a.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/ground_program?format=aspif").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    assert!(data.starts_with("asp 1 0 0\n"));
+    assert!(data.ends_with("0\n"));
+    let response = client.get("/ground_program?format=text").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    assert!(data.contains("c :- a."));
+    let response = client.get("/ground_program?format=xml").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+}
+#[test]
+fn test_theory_atoms() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/theory_atoms").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::theory_atoms failed! No control object."
+    );
+
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/add")
+        .body("#theory t { term { }; &a/0 : term, any }. &a { 1 }.")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/theory_atoms").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data[0]["term"]["type"], "Symbol");
+    assert_eq!(data[0]["term"]["name"], "a");
+    assert_eq!(data[0]["elements"][0]["tuple"][0]["number"], 1);
+}
+#[test]
+fn test_register_theory() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/register_theory/foo").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Unknown theory foo! Available theories are: con, dl."
+    );
+    let response = client.post("/register_theory/con").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Theory con registered.".into())
+    );
+}
+#[test]
+fn test_register_multiple_theories() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/register_theory/dl").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Theory dl registered.".into()));
+    let response = client.post("/register_theory/con").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Theory con registered.".into())
+    );
+    let response = client.get("/register_dl_theory").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::register_dl_theory failed! Theory dl already registered."
+    );
+    let response = client
+        .post("/add")
+        .body("&diff{ x-y } <= -1. &sum{ z } >= 2.")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Added data to Solver.".into()));
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Grounding.".into()));
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut body_string = client.get("/model").dispatch().into_string();
+    while body_string == Some("\"Running\"".into()) {
+        body_string = client.get("/model").dispatch().into_string();
+    }
+    let data: Value = serde_json::from_str(&body_string.unwrap()).unwrap();
+    let model: Vec<u8> = data["Model"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|byte| byte.as_u64().unwrap() as u8)
+        .collect();
+    // both theories contribute their assignment to the model
+    let model = String::from_utf8(model).unwrap();
+    let value = |name: &str| -> i64 {
+        model
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{}=", name)))
+            .unwrap()
+            .parse()
+            .unwrap()
+    };
+    assert!(value("x") - value("y") <= -1);
+    assert!(value("z") >= 2);
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+#[test]
+fn test_plugins() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/plugins").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("[]".into()));
+
+    let figment = Config::figment().merge((
+        "plugins",
+        serde_json::json!([{"name": "mydl", "path": "/nonexistent/libmydl.so"}]),
+    ));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/plugins").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data[0]["name"], "mydl");
+    assert_eq!(data[0]["version"], Value::Null);
+    assert!(data[0]["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("Could not load plugin mydl from /nonexistent/libmydl.so!"));
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/register_theory/mydl").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert!(data["msg"]
+        .as_str()
+        .unwrap()
+        .starts_with("Theory mydl is not available! Could not load plugin mydl"));
+}
+#[test]
+fn test_load_plugin() {
+    // the clingo-dl library the server is linked against serves as plugin
+    let path = libloading::library_filename("clingo-dl");
+    let path = path.to_str().unwrap();
+    let figment = Config::figment().merge((
+        "plugins",
+        serde_json::json!([
+            {"name": "mydl", "path": path, "prefix": "clingodl"},
+            {"name": "dl", "path": path, "prefix": "clingodl"}
+        ]),
+    ));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/plugins").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data[0]["name"], "mydl");
+    assert_eq!(data[0]["error"], Value::Null);
+    assert!(data[0]["version"].is_string());
+    assert_eq!(data[1]["version"], Value::Null);
+    assert_eq!(
+        data[1]["error"],
+        format!(
+            "Plugin dl in {} is not loaded! A theory named dl already exists.",
+            path
+        )
+    );
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/register_theory/mydl").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Theory mydl registered.".into())
+    );
+    let response = client.post("/add").body("&diff{ x-y } <= -1.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut body_string = client.get("/model").dispatch().into_string();
+    while body_string == Some("\"Running\"".into()) {
+        body_string = client.get("/model").dispatch().into_string();
+    }
+    let data: Value = serde_json::from_str(&body_string.unwrap()).unwrap();
+    let model: Vec<u8> = data["Model"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|byte| byte.as_u64().unwrap() as u8)
+        .collect();
+    let model = String::from_utf8(model).unwrap();
+    let value = |name: &str| -> i64 {
+        model
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{}=", name)))
+            .unwrap()
+            .parse()
+            .unwrap()
+    };
+    assert!(value("x") - value("y") <= -1);
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+#[test]
+fn test_theory_options() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/register_dl_theory")
+        .header(ContentType::JSON)
+        .body(r#"{"propagate":["full"]}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Could not parse theory option propagate! Expected a string, number or boolean."
+    );
+    let response = client
+        .post("/register_dl_theory")
+        .header(ContentType::JSON)
+        .body(r#"{"--no-such-option":"1"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Could not configure theory dl! Invalid value 1 for option no-such-option."
+    );
+    let response = client
+        .post("/register_con_theory")
+        .header(ContentType::JSON)
+        .body(r#"{"--min-int":-1000}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Clingcon theory registered.".into())
+    );
+}
+#[test]
+fn test_theory_statistics() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/statistics/theory").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::theory_statistics failed! No control object."
+    );
+
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/register_dl_theory").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("&diff{ x-y } <= -1.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut body_string = client.get("/model").dispatch().into_string();
+    while body_string == Some("\"Running\"".into()) {
+        body_string = client.get("/model").dispatch().into_string();
+    }
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.get("/statistics/theory?view=step").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert!(data["step"]["DifferenceLogic"].is_object());
+    assert!(data.get("accu").is_none());
+    let response = client.get("/statistics/theory").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert!(data["step"]["DifferenceLogic"].is_object());
+    assert!(data["accu"]["DifferenceLogic"].is_object());
+    let response = client.get("/statistics/theory?view=all").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Unknown statistics view all! Expected step, accu or both."
+    );
+}
+#[test]
+fn test_statistics_while_solving() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("{a;b}.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut body_string = client.get("/model").dispatch().into_string();
+    while body_string == Some("\"Running\"".into()) {
+        body_string = client.get("/model").dispatch().into_string();
+    }
+    let response = client.get("/statistics").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["progress"]["models"], 1.0);
+    assert_eq!(data["progress"]["finished"], 0.0);
+    assert!(data["progress"]["time"].as_f64().unwrap() >= 0.0);
+    for key in ["conflicts", "choices", "restarts"] {
+        assert!(data["progress"][key].as_f64().unwrap() >= 0.0);
+    }
+    assert_eq!(data["summary"], Value::Null);
+    let response = client.get("/statistics?path=progress.models").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("1.0".into()));
+    // the statistics of clingo are not faked while solving
+    let response = client.get("/statistics?path=summary.times").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        data["msg"],
+        "Solver::statistics failed! Statistics path summary.times is not available while solving."
+    );
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+#[test]
+fn test_statistics_paths() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("{a;b}.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/statistics?path=summary.times").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert!(data["total"].is_number());
+    let response = client
+        .post("/statistics")
+        .header(ContentType::JSON)
+        .body("[\"summary.times.total\",\"summary.models\"]")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert!(data["summary.times.total"].is_number());
+    assert!(data["summary.models"].is_object());
+    let response = client.get("/statistics?path=summary.foo").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::statistics failed! Unknown statistics path summary.foo."
+    );
+}
+#[test]
+fn test_user_statistics() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client
+        .post("/user_statistics")
+        .header(ContentType::JSON)
+        .body("{\"Preprocessing\":{\"removed\":3}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::add_user_statistics failed! No control object."
+    );
+
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/user_statistics")
+        .header(ContentType::JSON)
+        .body("{\"Preprocessing\":{\"removed\":3,\"passes\":[1,2]}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Added user statistics.".into())
+    );
+    let response = client
+        .post("/user_statistics")
+        .header(ContentType::JSON)
+        .body("{\"Preprocessing\":{\"removed\":4}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("a.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut body_string = client.get("/model").dispatch().into_string();
+    while body_string == Some("\"Running\"".into()) {
+        body_string = client.get("/model").dispatch().into_string();
+    }
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .get("/statistics?path=user_step.Preprocessing")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["removed"], 4.0);
+    assert_eq!(data["passes"][1], 2.0);
+    let response = client
+        .post("/user_statistics")
+        .header(ContentType::JSON)
+        .body("{\"Preprocessing\":\"many\"}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Could not parse user statistics! Expected numbers, arrays or objects."
+    );
+    // clingo can not change the type of an entry of the statistics
+    let response = client
+        .post("/user_statistics")
+        .header(ContentType::JSON)
+        .body("{\"Preprocessing\":{\"removed\":{\"rules\":1}}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::add_user_statistics failed! Entry Preprocessing.removed has type Value and can not be changed to Map."
+    );
+    let response = client.post("/register_theory/dl").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/user_statistics")
+        .header(ContentType::JSON)
+        .body("{\"DifferenceLogic\":{\"Time\":1}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::add_user_statistics failed! Entry DifferenceLogic is reserved for the statistics of theory dl."
+    );
+}
+#[test]
+fn test_configuration_paths() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/configuration/solve.models").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("\"0\"".into()));
+    let response = client
+        .put("/configuration/solve.models")
+        .body("3")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Set configuration solve.models.".into())
+    );
+    let response = client
+        .get("/configuration/solve.models?verbose=true")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "value");
+    assert_eq!(data["value"], "3");
+    assert!(data["description"].is_string());
+    let response = client.put("/configuration/solve.foo").body("3").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::set_configuration failed! Unknown configuration key solve.foo."
+    );
+    let response = client
+        .post("/set_configuration")
+        .header(ContentType::JSON)
+        .body("{\"solve\":{\"foo\":\"1\"}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::set_configuration failed! Unknown configuration key solve.foo."
+    );
+}
+#[test]
+fn test_create_with_arguments() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client
+        .post("/create")
+        .header(ContentType::JSON)
+        .body("[\"--models=3\",\"--opt-mode=optN\"]")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Created clingo Solver.".into())
+    );
+    let response = client.get("/configuration/solve.models").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("\"3\"".into()));
+    let response = client
+        .post("/create")
+        .header(ContentType::JSON)
+        .body("[\"--no-such-option\"]")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert!(data["msg"]
+        .as_str()
+        .unwrap()
+        .starts_with("Solver::create failed! Invalid arguments --no-such-option."));
+    let response = client
+        .post("/create")
+        .header(ContentType::JSON)
+        .body("[3]")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "Could not parse arguments! Expected an array of strings."
+    );
+}
+#[test]
+fn test_constants() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client
+        .post("/create")
+        .header(ContentType::JSON)
+        .body("{\"constants\":{\"n\":2}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/add")
+        .body("#const n=1. #const m=1. p(n). q(m). m.")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"parts\":{\"base\":[]},\"constants\":{\"m\":\"c\"}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Grounding.".into()));
+    let response = client.get("/ground_program?format=text").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    assert!(data.contains("p(2)."));
+    assert!(data.contains("q(c)."));
+    // only constants are replaced, not atoms of the same name
+    assert!(data.lines().any(|line| line == "m."));
+    assert!(!data.lines().any(|line| line == "c."));
+    // like `-c` the constants also apply to the programs grounded later
+    let response = client.post("/add").body("r(m).").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Grounding.".into()));
+    let response = client.get("/ground_program?format=text").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    assert!(data.contains("r(c)."));
+    // a constant grounded with one value can not be redefined
+    let response = client.post("/add").body("s(m).").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"parts\":{\"base\":[]},\"constants\":{\"m\":\"d\"}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert!(data["msg"].is_string());
+
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"parts\":{\"base\":[]},\"constants\":{\"N\":1}}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Could not parse constants! Invalid constant name N."
+    );
+}
+#[test]
+fn test_server_config() {
+    let figment = Config::figment()
+        .merge(("max_sessions", 1))
+        .merge(("default_arguments", ["--models=2"]));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Created clingo Solver.".into())
+    );
+    let response = client.get("/configuration/solve.models").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("\"2\"".into()));
+    let config: ServerConfig = Config::figment()
+        .merge(("job_workers", 0))
+        .extract()
+        .unwrap();
+    assert_eq!(
+        config.validate(),
+        Err("Invalid server configuration! job_workers has to be at least 1.".to_string())
+    );
+    // the interactive session holds the only session, so jobs wait for it
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body("{\"program\":\"a.\"}")
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let response = client.get(format!("/jobs/{}", data["id"])).dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["status"], "Queued");
+
+    // a running job holds the only session, so no interactive session can be created
+    let figment = Config::figment()
+        .merge(("max_sessions", 1))
+        .merge(("solve_timeout", 1));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body(
+            "{\"program\":\"p(1..14). h(1..13). 1{in(P,H):h(H)}1 :- p(P). \
+            :- in(P,H), in(Q,H), P<Q.\"}",
+        )
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    let running = data["id"].clone();
+    loop {
+        let response = client.get(format!("/jobs/{}", running)).dispatch();
+        let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+        if data["status"] == "Running" {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(
+        data["msg"],
+        "Could not create solver! All 1 sessions are in use by jobs."
+    );
+    wait_for_job(&client, &running);
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+#[test]
+fn test_solve_timeout() {
+    let figment = Config::figment().merge(("solve_timeout", 1));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/add")
+        .body("p(1..14). h(1..13). 1{in(P,H):h(H)}1 :- p(P). :- in(P,H), in(Q,H), P<Q.")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    // the search is cancelled without polling for models
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    let response = client.get("/statistics").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["progress"]["finished"], 1.0);
+    // the counters of the solvers are known once the search is done
+    assert!(data["progress"]["conflicts"].as_f64().unwrap() > 0.0);
+    assert!(data["progress"]["choices"].as_f64().unwrap() > 0.0);
+    let response = client.get("/model").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert_eq!(
+        &data["msg"],
+        "Solver::model failed! Search cancelled after the solve timeout of 1 seconds."
+    );
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+#[test]
+fn test_payload_too_large() {
+    let figment = Config::figment().merge(("max_body", 32));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("a. b :- a.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Added data to Solver.".into()));
+    let response = client
+        .post("/add")
+        .body("a. b :- a. c :- b. d :- c. e :- d. f :- e.")
+        .dispatch();
+    assert_eq!(response.status(), Status::PayloadTooLarge);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(data["type"], "PayloadTooLarge");
+    assert_eq!(&data["msg"], "The request body exceeds the limit of 32B!");
+    let response = client
+        .post("/facts")
+        .header(ContentType::JSON)
+        .body("{\"predicate\":\"edge\",\"tuples\":[[1,2],[2,3]]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::PayloadTooLarge);
+}
+#[test]
+fn test_compressed_upload() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use rocket::http::Header;
+    use std::io::Write;
+
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(b"a. b :- a.").unwrap();
+    let body = encoder.finish().unwrap();
+    let response = client
+        .post("/add")
+        .header(Header::new("Content-Encoding", "gzip"))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Added data to Solver.".into()));
+
+    let mut builder = tar::Builder::new(vec![]);
+    for (name, program) in [("base.lp", "c :- b."), ("README", "not a program")] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(program.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, program.as_bytes())
+            .unwrap();
+    }
+    let body = builder.into_inner().unwrap();
+    let response = client
+        .post("/add")
+        .header(ContentType::new("application", "x-tar"))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    // a compressed program is decoded into the workspace, so it includes the archive members
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(b"#include \"base.lp\". d :- c.").unwrap();
+    let body = encoder.finish().unwrap();
+    let response = client
+        .post("/add")
+        .header(Header::new("Content-Encoding", "gzip"))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.into_string(), Some("Added data to Solver.".into()));
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/ground_program?format=text").dispatch();
+    let data = response.into_string().unwrap();
+    assert!(data.contains("c."));
+    assert!(data.contains("d."));
+
+    let response = client
+        .post("/add")
+        .header(Header::new("Content-Encoding", "br"))
+        .body("a.")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "Unsupported content encoding br! Expected gzip or zstd."
+    );
+
+    let figment = Config::figment().merge(("max_body", 64));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&[b'%'; 1000]).unwrap();
+    let body = encoder.finish().unwrap();
+    let response = client
+        .post("/add")
+        .header(Header::new("Content-Encoding", "gzip"))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::PayloadTooLarge);
+}
+#[test]
+fn test_add_files() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = "--X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"main.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        #include \"instance.lp\". b :- a.\r\n\
+        --X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"instance.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        a.\r\n\
+        --X--\r\n";
+    let response = client
+        .post("/add")
+        .header(ContentType::new("multipart", "form-data").with_params(("boundary", "X")))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    // the included instance is only stored in the workspace
+    assert_eq!(
+        response.into_string(),
+        Some("Added main.lp to Solver.".into())
+    );
+    let response = client
+        .post("/add")
+        .body("#include \"instance.lp\". c :- b.")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/ground_program?format=text").dispatch();
+    let data = response.into_string().unwrap();
+    assert!(data.contains("c."));
+
+    let body = "--X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"../main.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        a.\r\n\
+        --X--\r\n";
+    let response = client
+        .post("/add")
+        .header(ContentType::new("multipart", "form-data").with_params(("boundary", "X")))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "Invalid file name \"../main.lp\"! Expected a file name without directories."
+    );
+
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = "--X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"x.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        x.\r\n\
+        --X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"y.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        y.\r\n\
+        --X\r\n\
+        Content-Disposition: form-data; name=\"main\"\r\n\r\n\
+        y.lp\r\n\
+        --X--\r\n";
+    let response = client
+        .post("/add")
+        .header(ContentType::new("multipart", "form-data").with_params(("boundary", "X")))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string(), Some("Added y.lp to Solver.".into()));
+    let body = "--X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"x.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        x.\r\n\
+        --X\r\n\
+        Content-Disposition: form-data; name=\"main\"\r\n\r\n\
+        z.lp\r\n\
+        --X--\r\n";
+    let response = client
+        .post("/add")
+        .header(ContentType::new("multipart", "form-data").with_params(("boundary", "X")))
+        .body(body)
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "Unknown main file z.lp! It is not one of the uploaded files."
+    );
+
+    // includes in comments and strings do not count
+    let body = "--X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"a.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        % #include \"b.lp\".\n\
+        %* #include \"b.lp\". *% s(\"#include \\\"b.lp\\\".\").\r\n\
+        --X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"b.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        b.\r\n\
+        --X--\r\n";
+    let response = client
+        .post("/add")
+        .header(ContentType::new("multipart", "form-data").with_params(("boundary", "X")))
+        .body(body)
+        .dispatch();
+    assert_eq!(
+        response.into_string(),
+        Some("Added a.lp, b.lp to Solver.".into())
+    );
+
+    let figment = Config::figment().merge(("max_body", 64));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = format!(
+        "--X\r\n\
+        Content-Disposition: form-data; name=\"files\"; filename=\"x.lp\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        {}\r\n\
+        --X--\r\n",
+        "%".repeat(1000)
+    );
+    let response = client
+        .post("/add")
+        .header(ContentType::new("multipart", "form-data").with_params(("boundary", "X")))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::PayloadTooLarge);
+}
+#[test]
+fn test_program_library() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.put("/programs/queens").body("a.").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "No program library! The server is started without library_dir."
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let figment = Config::figment().merge(("library_dir", dir.path()));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.put("/programs/queens").body("a.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["version"], "v1");
+    let response = client.put("/programs/queens").body("b :- a.").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["version"], "v2");
+    let sha256 = data["sha256"].as_str().unwrap().to_string();
+    // storing the latest version again does not create a new version
+    let response = client.put("/programs/queens").body("b :- a.").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["version"], "v2");
+    let response = client.get("/programs/queens").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data.as_array().unwrap().len(), 2);
+
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/add")
+        .header(ContentType::JSON)
+        .body("{\"program_ref\":\"queens@v1\"}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/add")
+        .header(ContentType::JSON)
+        .body("{\"program_ref\":\"queens\"}")
+        .dispatch();
+    assert_eq!(
+        response.into_string(),
+        Some(format!(
+            "Added program queens@v2 with sha256 {} to Solver.",
+            sha256
+        ))
+    );
+    let response = client
+        .post("/add")
+        .header(ContentType::JSON)
+        .body("{\"program_ref\":\"queens@v3\"}")
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "Unknown version v3 of program queens! The latest version is v2."
+    );
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/ground_program?format=text").dispatch();
+    let data = response.into_string().unwrap();
+    assert!(data.contains("b."));
+    // the session records the resolved references
+    let response = client.get("/session/export").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["commands"][1]["command"], "add_program_ref");
+    assert_eq!(data["commands"][1]["reference"], "queens@v1");
+    assert_eq!(data["commands"][2]["reference"], "queens@v2");
+
+    // the library survives a restart of the server
+    let figment = Config::figment().merge(("library_dir", dir.path()));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/programs/queens").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data.as_array().unwrap().len(), 2);
+
+    let response = client.put("/programs/queens.lp").body("a.").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "Invalid program name queens.lp! Expected letters, digits, '_' or '-'."
+    );
+}
+#[test]
+fn test_command_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.jsonl");
+    let figment = Config::figment().merge(("command_log", &path));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("a. #external e.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/assign_external")
+        .header(ContentType::JSON)
+        .body("{\"literal\":\"e\",\"truth_value\":\"True\"}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/resume").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    // failed commands are not logged
+    let response = client.post("/add").body("a :- .").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    let mut builder = tar::Builder::new(vec![]);
+    for (name, program) in [
+        ("main.lp", "#include \"inst.lp\". b :- x."),
+        ("inst.lp", "x."),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(program.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, program.as_bytes())
+            .unwrap();
+    }
+    let response = client
+        .post("/add")
+        .header(ContentType::new("application", "x-tar"))
+        .body(builder.into_inner().unwrap())
+        .dispatch();
+    assert_eq!(response.into_string(), Some("Added data to Solver.".into()));
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let commands: Vec<Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(commands.len(), 9);
+    assert_eq!(commands[0]["command"], "create");
+    // programs are logged by a copy in the files directory of the log
+    assert_eq!(commands[1]["command"], "add_file");
+    let file = commands[1]["program"]["file"].as_str().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("session.files").join(file)).unwrap(),
+        "a. #external e."
+    );
+    assert_eq!(commands[3]["command"], "assign_external");
+    // the members of an archive are logged by name, included members first
+    assert_eq!(commands[7]["command"], "add_file");
+    assert_eq!(commands[7]["name"], "inst.lp");
+    assert_eq!(commands[7]["entry"], false);
+    assert_eq!(commands[8]["name"], "main.lp");
+    assert_eq!(commands[8]["entry"], true);
+
+    let mut out = vec![];
+    command::replay(&path, &TheoryRegistry::default(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert!(lines[4].starts_with("5: model "));
+    assert!(lines[4].contains('a') && lines[4].contains('e'));
+    assert_eq!(lines[5], "6: Done");
+    assert_eq!(lines[6], "7: ok");
+    assert_eq!(lines[8], "9: ok");
+
+    // the replay stops at the first failing command
+    let path = dir.path().join("failing.jsonl");
+    std::fs::write(
+        &path,
+        "{\"command\":\"create\",\"arguments\":[]}\n\
+        {\"command\":\"add\",\"program\":\"a :- .\"}\n\
+        {\"command\":\"add\",\"program\":\"b.\"}\n",
+    )
+    .unwrap();
+    let mut out = vec![];
+    let error = command::replay(&path, &TheoryRegistry::default(), &mut out).unwrap_err();
+    assert!(error.message().contains("Command in line 2 failed."));
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.lines().count(), 2);
+    assert!(out.lines().nth(1).unwrap().starts_with("2: error "));
+}
+#[test]
+fn test_session_export() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/session/export").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(&data["msg"], "Solver::export failed! No control object.");
+
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("a. #external e.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/assign_external")
+        .header(ContentType::JSON)
+        .body("{\"literal\":\"e\",\"truth_value\":\"True\"}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/session/export").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let bundle = response.into_string().unwrap();
+    let data: Value = serde_json::from_str(&bundle).unwrap();
+    assert_eq!(data["commands"].as_array().unwrap().len(), 4);
+    // exported programs are inline
+    assert_eq!(data["commands"][1]["program"]["text"], "a. #external e.");
+
+    // a fresh server restores the session
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client
+        .post("/session/import")
+        .header(ContentType::JSON)
+        .body(&bundle)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string(),
+        Some("Imported session with 4 commands.".into())
+    );
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mut body_string = client.get("/model").dispatch().into_string();
+    while body_string == Some("\"Running\"".into()) {
+        body_string = client.get("/model").dispatch().into_string();
+    }
+    let data: Value = serde_json::from_str(&body_string.unwrap()).unwrap();
+    let model: Vec<u8> = data["Model"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|byte| byte.as_u64().unwrap() as u8)
+        .collect();
+    let model = String::from_utf8(model).unwrap();
+    assert!(model.contains('a') && model.contains('e'));
+
+    // the import stops at the first failing command
+    let response = client
+        .post("/session/import")
+        .header(ContentType::JSON)
+        .body(
+            "{\"commands\":[{\"command\":\"create\",\"arguments\":[]},\
+            {\"command\":\"add\",\"program\":\"a :- .\"},\
+            {\"command\":\"add\",\"program\":\"b.\"}]}",
+        )
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert!(data["msg"]
+        .as_str()
+        .unwrap()
+        .starts_with("Could not import session! Command 2 failed."));
+
+    let response = client
+        .post("/session/import")
+        .header(ContentType::JSON)
+        .body("{\"commands\":[{\"command\":\"solve\"}]}")
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "Could not import session! The first command has to create the solver."
+    );
+
+    // files of the server are not read
+    let response = client
+        .post("/session/import")
+        .header(ContentType::JSON)
+        .body(
+            "{\"commands\":[{\"command\":\"create\",\"arguments\":[]},\
+            {\"command\":\"aspif\",\"program\":{\"file\":\"/etc/passwd\"}}]}",
+        )
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert!(data["msg"]
+        .as_str()
+        .unwrap()
+        .starts_with("Could not import session! Unexpected command"));
+}
+#[test]
+fn test_runs() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client.get("/runs/abc-1").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(
+        &data["msg"],
+        "The result store is disabled! Set run_dir in the server configuration."
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let figment = Config::figment().merge(("run_dir", dir.path()));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client.get("/create").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post("/add").body("1{a;b}1.").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post("/ground")
+        .header(ContentType::JSON)
+        .body("{\"base\":[]}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    loop {
+        let data = client.get("/model").dispatch().into_string().unwrap();
+        if data == "\"Done\"" {
+            break;
+        }
+        if data != "\"Running\"" {
+            // polling the same model again does not record it twice
+            client.get("/model").dispatch();
+            client.get("/resume").dispatch();
+        }
+    }
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let data = client.get("/session").dispatch().into_string().unwrap();
+    let data: Value = serde_json::from_str(&data).unwrap();
+    let session = data["session"].as_str().unwrap().to_string();
+    let response = client.get(format!("/runs?session={}", session)).dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    let runs = data.as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0]["models"], 2);
+    assert_eq!(runs[0]["result"]["satisfiable"], true);
+    assert_eq!(runs[0]["result"]["exhausted"], true);
+
+    let id = runs[0]["id"].as_str().unwrap();
+    let response = client.get(format!("/runs/{}", id)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["inputs"].as_array().unwrap().len(), 3);
+    assert_eq!(data["inputs"][1]["program"]["text"], "1{a;b}1.");
+    assert_eq!(data["models"].as_array().unwrap().len(), 2);
+    assert!(data["statistics"]["summary"].is_object());
+
+    // failed commands are not part of the inputs and models are recorded without polling
+    let response = client.post("/add").body("c :- .").dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    let response = client.get("/solve").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    loop {
+        let data = client.get("/statistics").dispatch().into_string().unwrap();
+        let data: Value = serde_json::from_str(&data).unwrap();
+        if data["progress"]["models"] == 1.0 {
+            break;
+        }
+    }
+    let response = client.get("/close").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get(format!("/runs/{}-2", session)).dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["inputs"].as_array().unwrap().len(), 3);
+    assert_eq!(data["models"].as_array().unwrap().len(), 1);
+    assert_eq!(data["result"]["interrupted"], true);
+    // the inputs are stored once per session and the models are appended
+    let inputs = std::fs::read_to_string(dir.path().join(format!("{}.inputs.jsonl", session)));
+    assert_eq!(inputs.unwrap().lines().count(), 3);
+    let models = std::fs::read_to_string(dir.path().join(format!("{}-2.models.jsonl", session)));
+    assert_eq!(models.unwrap().lines().count(), 1);
+
+    let response = client.get("/runs?session=unknown").dispatch();
+    assert_eq!(response.into_string(), Some("[]".into()));
+}
+fn wait_for_job(client: &Client, id: &Value) -> Value {
+    loop {
+        let response = client.get(format!("/jobs/{}", id)).dispatch();
+        let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+        if data["status"] != "Queued" && data["status"] != "Running" {
+            return data;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+#[test]
+fn test_jobs() {
+    let client = Client::tracked(rocket()).unwrap();
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body("{\"program\":\"1{a;b}1.\"}")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["status"], "Queued");
+    let data = wait_for_job(&client, &data["id"]);
+    assert_eq!(data["status"], "Finished");
+    assert_eq!(data["models"].as_array().unwrap().len(), 2);
+    assert_eq!(data["result"]["satisfiable"], true);
+
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body("{\"program\":\"1{a;b}1.\",\"models\":1,\"assumptions\":[[\"b\",true]]}")
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    let data = wait_for_job(&client, &data["id"]);
+    assert_eq!(data["status"], "Finished");
+    assert_eq!(data["models"].as_array().unwrap().len(), 1);
+    assert_eq!(data["models"][0]["model"], "b\n");
+
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body("{\"program\":\"a.\",\"theory\":\"unknown\"}")
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    let data = wait_for_job(&client, &data["id"]);
+    assert_eq!(data["status"], "Failed");
+    assert_eq!(data["error"]["type"], "InternalError");
+
+    let response = client.get("/jobs/100").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["type"], "NotFound");
+    assert_eq!(&data["msg"], "Unknown job 100!");
+
+    // a job collects at least one model
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body("{\"program\":\"1{a;b}1.\",\"models\":0}")
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["type"], "InternalError");
+    assert!(data["msg"]
+        .as_str()
+        .unwrap()
+        .starts_with("Could not parse job"));
+
+    // while the only worker runs a job further jobs wait in the queue
+    let figment = Config::figment()
+        .merge(("job_workers", 1))
+        .merge(("job_queue", 1))
+        .merge(("max_sessions", 1))
+        .merge(("solve_timeout", 1));
+    let client = Client::tracked(build(figment)).unwrap();
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body(
+            "{\"program\":\"p(1..14). h(1..13). 1{in(P,H):h(H)}1 :- p(P). \
+            :- in(P,H), in(Q,H), P<Q.\"}",
+        )
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    let running = data["id"].clone();
+    loop {
+        let response = client.get(format!("/jobs/{}", running)).dispatch();
+        let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+        if data["status"] == "Running" {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body("{\"program\":\"a.\"}")
+        .dispatch();
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["status"], "Queued");
+    let queued = data["id"].clone();
+    let response = client
+        .post("/jobs")
+        .header(ContentType::JSON)
+        .body("{\"program\":\"a.\"}")
+        .dispatch();
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["type"], "ServiceUnavailable");
+    assert_eq!(&data["msg"], "Job queue is full! 1 jobs are waiting.");
+    let data = wait_for_job(&client, &running);
+    assert_eq!(data["status"], "Failed");
+    assert_eq!(
+        data["error"]["msg"],
+        "Solver::model failed! Search cancelled after the solve timeout of 1 seconds."
+    );
+    let data = wait_for_job(&client, &queued);
+    assert_eq!(data["status"], "Finished");
+
+    // only the latest finished jobs are kept
+    let figment = Config::figment().merge(("job_retention", 1));
+    let client = Client::tracked(build(figment)).unwrap();
+    let mut ids = vec![];
+    for _ in 0..2 {
+        let response = client
+            .post("/jobs")
+            .header(ContentType::JSON)
+            .body("{\"program\":\"a.\"}")
+            .dispatch();
+        let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+        let data = wait_for_job(&client, &data["id"]);
+        assert_eq!(data["status"], "Finished");
+        ids.push(data["id"].clone());
+    }
+    let response = client.get(format!("/jobs/{}", ids[0])).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let data: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(data["msg"], format!("Unknown job {}!", ids[0]));
+}
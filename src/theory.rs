@@ -0,0 +1,457 @@
+use crate::utils::ServerError;
+use clingo::{Control, Model, Statistics};
+use clingo_sys::{
+    clingo_ast_t, clingo_control_statistics, clingo_control_t, clingo_error_message,
+    clingo_model_t, clingo_statistics_t, clingo_symbol_t, clingo_symbol_to_string,
+    clingo_symbol_to_string_size,
+};
+use libloading::Library;
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::mem;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+
+/// Callback of `rewrite_ast` receiving the rewritten statements
+pub type AstCallback = unsafe extern "C" fn(ast: *mut clingo_ast_t, data: *mut c_void) -> bool;
+
+/// A value of the assignment of a theory like `clingodl_value_t`
+#[repr(C)]
+pub struct TheoryValue {
+    value_type: c_int,
+    value: TheoryValueData,
+}
+#[repr(C)]
+union TheoryValueData {
+    int_number: c_int,
+    double_number: f64,
+    symbol: clingo_symbol_t,
+}
+// `clingodl_value_t` in clingo-dl.h and `clingcon_value_t` in clingcon.h on 64 bit targets:
+// an int followed by an 8 byte aligned union of int, double and clingo_symbol_t (uint64_t)
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    assert!(mem::size_of::<c_int>() == 4);
+    assert!(mem::size_of::<clingo_symbol_t>() == 8);
+    assert!(mem::size_of::<TheoryValueData>() == 8);
+    assert!(mem::align_of::<TheoryValueData>() == 8);
+    assert!(mem::size_of::<TheoryValue>() == 16);
+    assert!(mem::align_of::<TheoryValue>() == 8);
+};
+
+/// The C interface of a theory as provided by clingo-dl and clingcon
+///
+/// The functions are named `<prefix>_create`, `<prefix>_register`, ... like `clingodl_create`,
+/// the theory itself is an opaque pointer.
+#[derive(Clone, Copy)]
+pub struct TheoryApi {
+    pub create: unsafe extern "C" fn(theory: *mut *mut c_void) -> bool,
+    pub destroy: unsafe extern "C" fn(theory: *mut c_void) -> bool,
+    pub configure:
+        unsafe extern "C" fn(theory: *mut c_void, key: *const c_char, value: *const c_char) -> bool,
+    pub register: unsafe extern "C" fn(theory: *mut c_void, control: *mut clingo_control_t) -> bool,
+    pub rewrite_ast: unsafe extern "C" fn(
+        theory: *mut c_void,
+        ast: *mut clingo_ast_t,
+        add: AstCallback,
+        data: *mut c_void,
+    ) -> bool,
+    pub prepare: unsafe extern "C" fn(theory: *mut c_void, control: *mut clingo_control_t) -> bool,
+    pub on_model: unsafe extern "C" fn(theory: *mut c_void, model: *mut clingo_model_t) -> bool,
+    pub on_statistics: unsafe extern "C" fn(
+        theory: *mut c_void,
+        step: *mut clingo_statistics_t,
+        accu: *mut clingo_statistics_t,
+    ) -> bool,
+    pub get_symbol: unsafe extern "C" fn(theory: *mut c_void, index: usize) -> clingo_symbol_t,
+    pub assignment_begin:
+        unsafe extern "C" fn(theory: *mut c_void, thread_id: u32, index: *mut usize),
+    pub assignment_next:
+        unsafe extern "C" fn(theory: *mut c_void, thread_id: u32, index: *mut usize) -> bool,
+    pub assignment_has_value:
+        unsafe extern "C" fn(theory: *mut c_void, thread_id: u32, index: usize) -> bool,
+    pub assignment_get_value: unsafe extern "C" fn(
+        theory: *mut c_void,
+        thread_id: u32,
+        index: usize,
+        value: *mut TheoryValue,
+    ),
+}
+
+/// The C interfaces of the theories linked into the server
+///
+/// The libraries of clingo-dl and clingcon are linked by the crates `clingo-dl-plugin` and
+/// `clingcon-plugin`, see `CLINGO_DL_LIBRARY_PATH` and `CLINGCON_LIBRARY_PATH` in the README.
+mod builtin {
+    use super::{AstCallback, TheoryApi, TheoryValue};
+    use clingo_sys::{
+        clingo_ast_t, clingo_control_t, clingo_model_t, clingo_statistics_t, clingo_symbol_t,
+    };
+    use std::os::raw::{c_char, c_void};
+
+    extern crate clingcon_plugin as _;
+    extern crate clingo_dl_plugin as _;
+
+    extern "C" {
+        fn clingodl_create(theory: *mut *mut c_void) -> bool;
+        fn clingodl_destroy(theory: *mut c_void) -> bool;
+        fn clingodl_configure(
+            theory: *mut c_void,
+            key: *const c_char,
+            value: *const c_char,
+        ) -> bool;
+        fn clingodl_register(theory: *mut c_void, control: *mut clingo_control_t) -> bool;
+        fn clingodl_rewrite_ast(
+            theory: *mut c_void,
+            ast: *mut clingo_ast_t,
+            add: AstCallback,
+            data: *mut c_void,
+        ) -> bool;
+        fn clingodl_prepare(theory: *mut c_void, control: *mut clingo_control_t) -> bool;
+        fn clingodl_on_model(theory: *mut c_void, model: *mut clingo_model_t) -> bool;
+        fn clingodl_on_statistics(
+            theory: *mut c_void,
+            step: *mut clingo_statistics_t,
+            accu: *mut clingo_statistics_t,
+        ) -> bool;
+        fn clingodl_get_symbol(theory: *mut c_void, index: usize) -> clingo_symbol_t;
+        fn clingodl_assignment_begin(theory: *mut c_void, thread_id: u32, index: *mut usize);
+        fn clingodl_assignment_next(theory: *mut c_void, thread_id: u32, index: *mut usize)
+            -> bool;
+        fn clingodl_assignment_has_value(theory: *mut c_void, thread_id: u32, index: usize)
+            -> bool;
+        fn clingodl_assignment_get_value(
+            theory: *mut c_void,
+            thread_id: u32,
+            index: usize,
+            value: *mut TheoryValue,
+        );
+    }
+    extern "C" {
+        fn clingcon_create(theory: *mut *mut c_void) -> bool;
+        fn clingcon_destroy(theory: *mut c_void) -> bool;
+        fn clingcon_configure(
+            theory: *mut c_void,
+            key: *const c_char,
+            value: *const c_char,
+        ) -> bool;
+        fn clingcon_register(theory: *mut c_void, control: *mut clingo_control_t) -> bool;
+        fn clingcon_rewrite_ast(
+            theory: *mut c_void,
+            ast: *mut clingo_ast_t,
+            add: AstCallback,
+            data: *mut c_void,
+        ) -> bool;
+        fn clingcon_prepare(theory: *mut c_void, control: *mut clingo_control_t) -> bool;
+        fn clingcon_on_model(theory: *mut c_void, model: *mut clingo_model_t) -> bool;
+        fn clingcon_on_statistics(
+            theory: *mut c_void,
+            step: *mut clingo_statistics_t,
+            accu: *mut clingo_statistics_t,
+        ) -> bool;
+        fn clingcon_get_symbol(theory: *mut c_void, index: usize) -> clingo_symbol_t;
+        fn clingcon_assignment_begin(theory: *mut c_void, thread_id: u32, index: *mut usize);
+        fn clingcon_assignment_next(theory: *mut c_void, thread_id: u32, index: *mut usize)
+            -> bool;
+        fn clingcon_assignment_has_value(theory: *mut c_void, thread_id: u32, index: usize)
+            -> bool;
+        fn clingcon_assignment_get_value(
+            theory: *mut c_void,
+            thread_id: u32,
+            index: usize,
+            value: *mut TheoryValue,
+        );
+    }
+
+    pub const DL: TheoryApi = TheoryApi {
+        create: clingodl_create,
+        destroy: clingodl_destroy,
+        configure: clingodl_configure,
+        register: clingodl_register,
+        rewrite_ast: clingodl_rewrite_ast,
+        prepare: clingodl_prepare,
+        on_model: clingodl_on_model,
+        on_statistics: clingodl_on_statistics,
+        get_symbol: clingodl_get_symbol,
+        assignment_begin: clingodl_assignment_begin,
+        assignment_next: clingodl_assignment_next,
+        assignment_has_value: clingodl_assignment_has_value,
+        assignment_get_value: clingodl_assignment_get_value,
+    };
+    pub const CON: TheoryApi = TheoryApi {
+        create: clingcon_create,
+        destroy: clingcon_destroy,
+        configure: clingcon_configure,
+        register: clingcon_register,
+        rewrite_ast: clingcon_rewrite_ast,
+        prepare: clingcon_prepare,
+        on_model: clingcon_on_model,
+        on_statistics: clingcon_on_statistics,
+        get_symbol: clingcon_get_symbol,
+        assignment_begin: clingcon_assignment_begin,
+        assignment_next: clingcon_assignment_next,
+        assignment_has_value: clingcon_assignment_has_value,
+        assignment_get_value: clingcon_assignment_get_value,
+    };
+}
+
+/// The C object wrapped by a control object, the C interfaces of the theories work on it
+pub fn control_ptr(ctl: &mut Control) -> *mut clingo_control_t {
+    NonNull::<clingo_control_t>::from(ctl).as_ptr()
+}
+/// The C object of a control object that is solving in a solve handle
+///
+/// It is only used under the lock of the solver owning the handle.
+pub struct RunningControl(*mut clingo_control_t);
+unsafe impl Send for RunningControl {}
+impl RunningControl {
+    pub fn new(ctl: &mut Control) -> RunningControl {
+        RunningControl(control_ptr(ctl))
+    }
+    /// The statistics of the control object, `None` if clingo does not provide them right now
+    pub fn statistics(&self) -> Option<&Statistics> {
+        let mut stats: *const clingo_statistics_t = ptr::null();
+        if !unsafe { clingo_control_statistics(self.0, &mut stats) } || stats.is_null() {
+            return None;
+        }
+        Some(unsafe { &*(stats as *const Statistics) })
+    }
+}
+fn model_ptr(model: &mut Model) -> *mut clingo_model_t {
+    NonNull::<clingo_model_t>::from(model).as_ptr()
+}
+fn statistics_ptr(stats: &mut Statistics) -> *mut clingo_statistics_t {
+    NonNull::<clingo_statistics_t>::from(stats).as_ptr()
+}
+/// The message of the last error of a clingo C function
+pub fn clingo_error(context: &str) -> ServerError {
+    let msg = unsafe { clingo_error_message() };
+    let msg = if msg.is_null() {
+        "Unknown error.".to_string()
+    } else {
+        unsafe { CStr::from_ptr(msg) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    ServerError::InternalError(format!("{} {}", context, msg))
+}
+fn symbol_to_string(symbol: clingo_symbol_t) -> String {
+    let mut size = 0;
+    if !unsafe { clingo_symbol_to_string_size(symbol, &mut size) } {
+        return String::new();
+    }
+    let mut buf = vec![0 as c_char; size];
+    if !unsafe { clingo_symbol_to_string(symbol, buf.as_mut_ptr(), size) } {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(buf.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+unsafe extern "C" fn add_ast(ast: *mut clingo_ast_t, data: *mut c_void) -> bool {
+    let add = &mut *(data as *mut &mut dyn FnMut(*mut clingo_ast_t) -> bool);
+    add(ast)
+}
+
+/// Object safe interface of the theories a session can use
+pub trait TheoryPlugin {
+    fn configure(&mut self, key: &str, value: &str) -> bool;
+    fn register(&mut self, ctl: &mut Control) -> bool;
+    /// Rewrite a statement and pass the resulting statements to `add`
+    fn rewrite_ast(
+        &mut self,
+        ast: *mut clingo_ast_t,
+        add: &mut dyn FnMut(*mut clingo_ast_t) -> bool,
+    ) -> bool;
+    fn prepare(&mut self, ctl: &mut Control) -> bool;
+    fn on_model(&mut self, model: &mut Model) -> bool;
+    fn on_statistics(&mut self, step: &mut Statistics, akku: &mut Statistics) -> bool;
+    /// The assigned symbols and their values in the last model of a solver thread
+    fn assignment(&mut self, thread_id: u32) -> Vec<(String, String)>;
+}
+
+/// A theory driven through its C interface
+pub struct CTheory {
+    api: TheoryApi,
+    theory: *mut c_void,
+    // the library providing `api` of a plugin, unloaded after the theory is destroyed
+    _library: Option<Arc<Library>>,
+}
+impl CTheory {
+    /// Create a theory, the functions of `api` must stay valid as long as `library` is loaded
+    pub fn create(api: TheoryApi, library: Option<Arc<Library>>) -> Result<CTheory, ServerError> {
+        let mut theory = ptr::null_mut();
+        if !unsafe { (api.create)(&mut theory) } || theory.is_null() {
+            return Err(clingo_error("Could not create theory!"));
+        }
+        Ok(CTheory {
+            api,
+            theory,
+            _library: library,
+        })
+    }
+}
+impl Drop for CTheory {
+    fn drop(&mut self) {
+        unsafe { (self.api.destroy)(self.theory) };
+    }
+}
+impl TheoryPlugin for CTheory {
+    fn configure(&mut self, key: &str, value: &str) -> bool {
+        match (CString::new(key), CString::new(value)) {
+            (Ok(key), Ok(value)) => unsafe {
+                (self.api.configure)(self.theory, key.as_ptr(), value.as_ptr())
+            },
+            _ => false,
+        }
+    }
+    fn register(&mut self, ctl: &mut Control) -> bool {
+        unsafe { (self.api.register)(self.theory, control_ptr(ctl)) }
+    }
+    fn rewrite_ast(
+        &mut self,
+        ast: *mut clingo_ast_t,
+        mut add: &mut dyn FnMut(*mut clingo_ast_t) -> bool,
+    ) -> bool {
+        let data = &mut add as *mut &mut dyn FnMut(*mut clingo_ast_t) -> bool as *mut c_void;
+        unsafe { (self.api.rewrite_ast)(self.theory, ast, add_ast, data) }
+    }
+    fn prepare(&mut self, ctl: &mut Control) -> bool {
+        unsafe { (self.api.prepare)(self.theory, control_ptr(ctl)) }
+    }
+    fn on_model(&mut self, model: &mut Model) -> bool {
+        unsafe { (self.api.on_model)(self.theory, model_ptr(model)) }
+    }
+    fn on_statistics(&mut self, step: &mut Statistics, akku: &mut Statistics) -> bool {
+        unsafe { (self.api.on_statistics)(self.theory, statistics_ptr(step), statistics_ptr(akku)) }
+    }
+    fn assignment(&mut self, thread_id: u32) -> Vec<(String, String)> {
+        let mut assignment = vec![];
+        let mut index = 0;
+        unsafe {
+            (self.api.assignment_begin)(self.theory, thread_id, &mut index);
+            while (self.api.assignment_next)(self.theory, thread_id, &mut index) {
+                if !(self.api.assignment_has_value)(self.theory, thread_id, index) {
+                    continue;
+                }
+                let symbol = (self.api.get_symbol)(self.theory, index);
+                let mut value = TheoryValue {
+                    value_type: 0,
+                    value: TheoryValueData { int_number: 0 },
+                };
+                (self.api.assignment_get_value)(self.theory, thread_id, index, &mut value);
+                let value = match value.value_type {
+                    0 => value.value.int_number.to_string(),
+                    1 => value.value.double_number.to_string(),
+                    _ => symbol_to_string(value.value.symbol),
+                };
+                assignment.push((symbol_to_string(symbol), value));
+            }
+        }
+        assignment
+    }
+}
+
+type TheoryFactory = Box<dyn Fn() -> Result<Box<dyn TheoryPlugin>, ServerError> + Send + Sync>;
+
+struct TheoryEntry {
+    factory: TheoryFactory,
+    statistics: Option<String>,
+}
+/// A fresh theory together with the key of its entry in the user statistics
+pub struct TheoryInstance {
+    pub theory: Box<dyn TheoryPlugin>,
+    pub statistics: Option<String>,
+}
+impl TheoryInstance {
+    /// Pass the options to the theory, all rejected options are reported in the error
+    pub fn configure(
+        &mut self,
+        name: &str,
+        options: &[(String, String)],
+    ) -> Result<(), ServerError> {
+        let rejected: Vec<String> = options
+            .iter()
+            .filter(|(key, value)| !self.theory.configure(key, value))
+            .map(|(key, value)| format!("Invalid value {} for option {}.", value, key))
+            .collect();
+        if rejected.is_empty() {
+            Ok(())
+        } else {
+            Err(ServerError::InternalError(format!(
+                "Could not configure theory {}! {}",
+                name,
+                rejected.join(" ")
+            )))
+        }
+    }
+}
+
+/// The theories that can be registered in a session by name
+pub struct TheoryRegistry {
+    factories: BTreeMap<String, TheoryEntry>,
+    unavailable: BTreeMap<String, String>,
+}
+impl Default for TheoryRegistry {
+    /// A registry with the built-in theories `dl` and `con`
+    fn default() -> Self {
+        let mut registry = TheoryRegistry {
+            factories: BTreeMap::new(),
+            unavailable: BTreeMap::new(),
+        };
+        registry.insert("dl", Some("DifferenceLogic"), || {
+            Ok(Box::new(CTheory::create(builtin::DL, None)?))
+        });
+        registry.insert("con", Some("Clingcon"), || {
+            Ok(Box::new(CTheory::create(builtin::CON, None)?))
+        });
+        registry
+    }
+}
+impl TheoryRegistry {
+    /// Add a theory, `statistics` is the key under which the theory adds its user statistics
+    pub fn insert<F>(&mut self, name: &str, statistics: Option<&str>, factory: F)
+    where
+        F: Fn() -> Result<Box<dyn TheoryPlugin>, ServerError> + Send + Sync + 'static,
+    {
+        self.factories.insert(
+            name.to_string(),
+            TheoryEntry {
+                factory: Box::new(factory),
+                statistics: statistics.map(|key| key.to_string()),
+            },
+        );
+    }
+    /// Remember a theory that could not be loaded together with the reason
+    pub fn insert_unavailable(&mut self, name: &str, error: &str) {
+        self.unavailable.insert(name.to_string(), error.to_string());
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.keys().map(|name| name.as_str()).collect()
+    }
+    /// Create a fresh instance of the theory registered under `name`
+    pub fn create(&self, name: &str) -> Result<TheoryInstance, ServerError> {
+        match self.factories.get(name) {
+            Some(entry) => Ok(TheoryInstance {
+                theory: (entry.factory)()?,
+                statistics: entry.statistics.clone(),
+            }),
+            None if self.unavailable.contains_key(name) => {
+                Err(ServerError::InternalError(format!(
+                    "Theory {} is not available! {}",
+                    name, self.unavailable[name]
+                )))
+            }
+            None => Err(ServerError::InternalError(format!(
+                "Unknown theory {}! Available theories are: {}.",
+                name,
+                self.names().join(", ")
+            ))),
+        }
+    }
+}
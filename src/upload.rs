@@ -0,0 +1,251 @@
+use crate::utils::{too_large, ServerError};
+use flate2::read::GzDecoder;
+use rocket::data::ByteUnit;
+use rocket::fs::TempFile;
+use rocket::request::{self, FromRequest, Request};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tempfile::TempDir;
+
+/// The headers describing how an uploaded program is packed
+pub struct UploadHeaders {
+    encoding: Option<String>,
+    tar: bool,
+}
+impl UploadHeaders {
+    /// Whether the body is an uncompressed program
+    pub fn is_plain(&self) -> bool {
+        self.encoding.is_none() && !self.tar
+    }
+    /// Whether the body is a tar archive of program files
+    pub fn is_tar(&self) -> bool {
+        self.tar
+    }
+}
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UploadHeaders {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let encoding = request
+            .headers()
+            .get_one("Content-Encoding")
+            .map(|encoding| encoding.trim().to_lowercase())
+            .filter(|encoding| encoding != "identity");
+        let tar = request
+            .content_type()
+            .map_or(false, |ct| ct.top() == "application" && ct.sub() == "x-tar");
+        request::Outcome::Success(UploadHeaders { encoding, tar })
+    }
+}
+
+/// A reader that fails once more than `remaining` bytes are read
+struct Limited<R> {
+    inner: R,
+    remaining: u64,
+    exceeded: Rc<Cell<bool>>,
+}
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n as u64 > self.remaining {
+            self.exceeded.set(true);
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "decompressed size exceeds the limit",
+            ));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Decode the uploaded body and return the program files
+///
+/// Compressed bodies are decompressed into a new `add-*.lp` file and `.tar` archives unpacked into `dir`,
+/// only the `.lp` files of an archive are returned.
+/// The decompressed size is subject to `limit` like the body itself.
+pub fn unpack(
+    body: &Path,
+    headers: &UploadHeaders,
+    limit: ByteUnit,
+    dir: &Path,
+) -> Result<Vec<PathBuf>, ServerError> {
+    if headers.is_plain() {
+        return Ok(vec![body.to_path_buf()]);
+    }
+    let file = File::open(body)?;
+    let reader: Box<dyn Read> = match headers.encoding.as_deref() {
+        None => Box::new(file),
+        Some("gzip") | Some("x-gzip") => Box::new(GzDecoder::new(file)),
+        Some("zstd") => Box::new(zstd::stream::read::Decoder::new(file)?),
+        Some(encoding) => {
+            return Err(ServerError::InternalError(format!(
+                "Unsupported content encoding {}! Expected gzip or zstd.",
+                encoding
+            )))
+        }
+    };
+    let exceeded = Rc::new(Cell::new(false));
+    let mut reader = Limited {
+        inner: reader,
+        remaining: limit.as_u64(),
+        exceeded: exceeded.clone(),
+    };
+    let result = if headers.tar {
+        unpack_tar(&mut reader, dir)
+    } else {
+        // a compressed program is decoded next to the uploaded programs
+        tempfile::Builder::new()
+            .prefix("add-")
+            .suffix(".lp")
+            .tempfile_in(dir)
+            .and_then(|mut file| {
+                io::copy(&mut reader, &mut file)?;
+                file.keep().map_err(|e| e.error)
+            })
+            .map(|(_, path)| vec![path])
+    };
+    match result {
+        Ok(files) => Ok(files),
+        Err(_) if exceeded.get() => Err(too_large(limit)),
+        Err(e) => Err(ServerError::InternalError(format!(
+            "Could not unpack program upload! {}",
+            e
+        ))),
+    }
+}
+
+fn unpack_tar(reader: impl Read, dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let is_program = path.extension().map_or(false, |ext| ext == "lp");
+        if is_program && entry.header().entry_type().is_file() && entry.unpack_in(dir)? {
+            files.push(dir.join(path));
+        }
+    }
+    Ok(files)
+}
+
+/// The directory of a session holding its uploaded program files
+#[derive(Default)]
+pub struct Workspace {
+    dir: Option<TempDir>,
+}
+impl Workspace {
+    /// The directory of the workspace, it is created on first use
+    pub fn dir(&mut self) -> Result<PathBuf, ServerError> {
+        if self.dir.is_none() {
+            self.dir = Some(tempfile::Builder::new().prefix("cl-server-").tempdir()?);
+        }
+        Ok(self.dir.as_ref().unwrap().path().to_path_buf())
+    }
+    /// The directory of the workspace if files have been uploaded
+    pub fn current(&self) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.path().to_path_buf())
+    }
+}
+
+/// The files included with `#include "file".` by the program stored under `name`
+///
+/// Comments and string literals are skipped, so only directives of the program count.
+fn includes(name: &str, program: &str) -> Vec<PathBuf> {
+    let dir = Path::new(name).parent().unwrap_or_else(|| Path::new(""));
+    let mut files = vec![];
+    let mut rest = program;
+    while let Some(c) = rest.chars().next() {
+        if let Some(comment) = rest.strip_prefix("%*") {
+            rest = comment.find("*%").map_or("", |end| &comment[end + 2..]);
+        } else if c == '%' {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if c == '"' {
+            rest = &rest[string_len(rest)..];
+        } else if let Some(directive) = rest.strip_prefix("#include") {
+            rest = directive.trim_start();
+            if rest.starts_with('"') {
+                let len = string_len(rest);
+                let file = rest[1..len].trim_end_matches('"');
+                files.push(dir.join(file.trim_start_matches("./")));
+                rest = &rest[len..];
+            }
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    files
+}
+
+/// The length of the string literal at the start of `text` including its quotes
+fn string_len(text: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The indices of the uploaded files to add
+///
+/// These are the files named in `main` in the given order,
+/// without `main` every file that no other uploaded file includes.
+pub fn entry_files(
+    names: &[String],
+    programs: &[String],
+    main: &[String],
+) -> Result<Vec<usize>, ServerError> {
+    if !main.is_empty() {
+        return main
+            .iter()
+            .map(|entry| {
+                names.iter().position(|name| name == entry).ok_or_else(|| {
+                    ServerError::InternalError(format!(
+                        "Unknown main file {}! It is not one of the uploaded files.",
+                        entry
+                    ))
+                })
+            })
+            .collect();
+    }
+    let included: Vec<Vec<PathBuf>> = names
+        .iter()
+        .zip(programs)
+        .map(|(name, program)| includes(name, program))
+        .collect();
+    Ok((0..names.len())
+        .filter(|&i| {
+            !included
+                .iter()
+                .enumerate()
+                .any(|(j, files)| j != i && files.iter().any(|file| file == Path::new(&names[i])))
+        })
+        .collect())
+}
+
+/// The name of an uploaded file in the workspace, directories are not allowed
+pub fn workspace_file_name(file: &TempFile<'_>) -> Result<String, ServerError> {
+    let raw = file
+        .raw_name()
+        .map(|name| name.dangerous_unsafe_unsanitized_raw().as_str())
+        .unwrap_or("");
+    let path = Path::new(raw);
+    match (path.file_name(), path.parent()) {
+        (Some(name), Some(parent)) if parent.as_os_str().is_empty() => {
+            Ok(name.to_string_lossy().into_owned())
+        }
+        _ => Err(ServerError::InternalError(format!(
+            "Invalid file name {:?}! Expected a file name without directories.",
+            raw
+        ))),
+    }
+}
@@ -1,23 +1,40 @@
-use clingcon_plugin::ConTheory;
+use crate::convert::is_predicate_name;
+use crate::ground_program::{AtomIds, GroundProgram, GroundProgramRecorder};
+use crate::runs::RunStore;
+use crate::theory::{
+    clingo_error, control_ptr, AstCallback, RunningControl, TheoryInstance, TheoryPlugin,
+};
 use clingo::{
-    ast, control, ClingoError, Configuration, ConfigurationType, Control, DefaultCtx,
-    GenericSolveHandle, Id, Model, Part, ShowType, SolveHandle, SolveMode, SolverLiteral,
-    Statistics, StatisticsType, Symbol, SymbolicAtoms, TruthValue,
+    control, Backend, ClingoError, Configuration, ConfigurationType, Control, DefaultCtx,
+    GenericSolveHandle, Id, Model, Part, ShowType, SolveMode, SolveResult, SolverLiteral,
+    Statistics, StatisticsType, Symbol, SymbolicAtoms, TermType, TheoryAtoms, TruthValue,
+};
+use clingo_sys::{
+    clingo_ast_acquire, clingo_ast_attribute_e_clingo_ast_attribute_name,
+    clingo_ast_attribute_get_string, clingo_ast_get_type, clingo_ast_parse_files,
+    clingo_ast_parse_string, clingo_ast_release, clingo_ast_t,
+    clingo_ast_type_e_clingo_ast_type_definition, clingo_program_builder_add,
+    clingo_program_builder_begin, clingo_program_builder_end, clingo_program_builder_init,
 };
-use clingo_dl_plugin::DLTheory;
-type DLSolveHandle = GenericSolveHandle<DefaultCtx, DLEventHandler>;
-type ConSolveHandle = GenericSolveHandle<DefaultCtx, ConEventHandler>;
-use clingo::theory::Theory;
+use parking_lot::Mutex;
+use rocket::data::{ByteUnit, Data};
+use rocket::http::Status;
 use rocket::response::{self, Responder};
 use rocket::serde::json::Json;
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 use std::cell::RefCell;
 use std::cmp;
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
 use std::fmt::Debug;
 use std::io;
 use std::io::Read;
+use std::os::raw::c_void;
+use std::path::Path;
+use std::ptr;
 use std::rc::Rc;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -28,13 +45,51 @@ pub enum ServerError {
     IOError(#[from] io::Error),
     #[error("InternalError:")]
     InternalError(String),
+    #[error("PayloadTooLarge:")]
+    PayloadTooLarge(String),
+    #[error("ServiceUnavailable:")]
+    ServiceUnavailable(String),
+    #[error("NotFound:")]
+    NotFound(String),
 }
 impl<'r> Responder<'r, 'static> for ServerError {
     fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = match self {
+            ServerError::PayloadTooLarge(_) => Status::PayloadTooLarge,
+            ServerError::ServiceUnavailable(_) => Status::ServiceUnavailable,
+            ServerError::NotFound(_) => Status::NotFound,
+            _ => Status::Ok,
+        };
         let json = Json(self);
-        json.respond_to(request)
+        let mut response = json.respond_to(request)?;
+        response.set_status(status);
+        Ok(response)
+    }
+}
+impl ServerError {
+    /// The message of the error as in its json form
+    pub fn message(&self) -> String {
+        match self {
+            ServerError::ClingoError(e) => format!("{}", e),
+            ServerError::IOError(e) => format!("{}", e),
+            ServerError::InternalError(msg)
+            | ServerError::PayloadTooLarge(msg)
+            | ServerError::ServiceUnavailable(msg)
+            | ServerError::NotFound(msg) => msg.clone(),
+        }
     }
 }
+pub fn too_large(limit: ByteUnit) -> ServerError {
+    ServerError::PayloadTooLarge(format!("The request body exceeds the limit of {}!", limit))
+}
+/// Read the request body, bodies exceeding the limit are rejected instead of truncated
+pub async fn read_body(data: Data<'_>, limit: ByteUnit) -> Result<String, ServerError> {
+    let cap = data.open(limit).into_string().await?;
+    if !cap.is_complete() {
+        return Err(too_large(limit));
+    }
+    Ok(cap.into_inner())
+}
 impl Serialize for ServerError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -54,6 +109,18 @@ impl Serialize for ServerError {
                 s.serialize_field("type", "InternalError")?;
                 s.serialize_field("msg", msg)?;
             }
+            ServerError::PayloadTooLarge(msg) => {
+                s.serialize_field("type", "PayloadTooLarge")?;
+                s.serialize_field("msg", msg)?;
+            }
+            ServerError::ServiceUnavailable(msg) => {
+                s.serialize_field("type", "ServiceUnavailable")?;
+                s.serialize_field("msg", msg)?;
+            }
+            ServerError::NotFound(msg) => {
+                s.serialize_field("type", "NotFound")?;
+                s.serialize_field("msg", msg)?;
+            }
         };
         s.end()
     }
@@ -72,13 +139,84 @@ pub enum ModelResult {
 
 use serde::ser::SerializeMap;
 use serde::ser::SerializeSeq;
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum StatisticsResult {
     Value(f64),
     Array(Vec<StatisticsResult>),
     Map(Vec<(String, StatisticsResult)>),
     Empty,
 }
+impl StatisticsResult {
+    fn statistics_type(&self) -> StatisticsType {
+        match self {
+            Self::Value(_) => StatisticsType::Value,
+            Self::Array(_) => StatisticsType::Array,
+            Self::Map(_) => StatisticsType::Map,
+            Self::Empty => StatisticsType::Empty,
+        }
+    }
+    /// Merge another tree into this one, values and arrays of the other tree replace the old ones
+    pub fn merge(&mut self, other: StatisticsResult) {
+        match (self, other) {
+            (Self::Map(array), Self::Map(other)) => {
+                for (name, entry) in other {
+                    match array.iter_mut().find(|(k, _)| *k == name) {
+                        Some((_, old)) => old.merge(entry),
+                        None => array.push((name, entry)),
+                    }
+                }
+            }
+            (old, other) => *old = other,
+        }
+    }
+    /// Check that merging `other` keeps the type of every entry clingo already knows
+    ///
+    /// The statistics object of clingo can not change the type of an entry once it is written.
+    fn check_merge(&self, other: &StatisticsResult, path: &str) -> Result<(), String> {
+        let subpath = |name: &str| {
+            if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", path, name)
+            }
+        };
+        match (self, other) {
+            (Self::Map(array), Self::Map(other)) => {
+                for (name, entry) in other {
+                    if let Some((_, old)) = array.iter().find(|(k, _)| k == name) {
+                        old.check_merge(entry, &subpath(name))?;
+                    }
+                }
+                Ok(())
+            }
+            (Self::Array(array), Self::Array(other)) => {
+                for (i, (old, entry)) in array.iter().zip(other).enumerate() {
+                    old.check_merge(entry, &subpath(&i.to_string()))?;
+                }
+                Ok(())
+            }
+            (Self::Value(_), Self::Value(_)) | (Self::Empty, _) | (_, Self::Empty) => Ok(()),
+            (old, other) => Err(format!(
+                "Entry {} has type {:?} and can not be changed to {:?}.",
+                path,
+                old.statistics_type(),
+                other.statistics_type()
+            )),
+        }
+    }
+    /// The entry at a dotted path
+    fn at(&self, path: &str) -> Option<&StatisticsResult> {
+        let mut entry = self;
+        for segment in path.split('.') {
+            entry = match entry {
+                Self::Map(array) => array.iter().find(|(k, _)| k == segment).map(|(_, v)| v)?,
+                Self::Array(array) => array.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(entry)
+    }
+}
 impl Serialize for StatisticsResult {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -105,6 +243,27 @@ impl Serialize for StatisticsResult {
     }
 }
 
+/// Which of the user statistics to show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatisticsView {
+    Step,
+    Accu,
+    Both,
+}
+impl StatisticsView {
+    pub fn parse(view: &str) -> Result<StatisticsView, ServerError> {
+        match view {
+            "step" => Ok(StatisticsView::Step),
+            "accu" => Ok(StatisticsView::Accu),
+            "both" => Ok(StatisticsView::Both),
+            v => Err(ServerError::InternalError(format!(
+                "Unknown statistics view {}! Expected step, accu or both.",
+                v
+            ))),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ConfigurationResult {
     Value(String),
@@ -135,29 +294,239 @@ impl Serialize for ConfigurationResult {
         }
     }
 }
-pub struct DLEventHandler {
-    theory: Rc<RefCell<DLTheory>>,
+/// A configuration entry with its description and type
+#[derive(Debug, Serialize)]
+pub struct ConfigurationDescription {
+    #[serde(rename = "type")]
+    configuration_type: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elements: Option<Vec<ConfigurationDescription>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entries: Option<BTreeMap<String, ConfigurationDescription>>,
 }
-impl clingo::SolveEventHandler for DLEventHandler {
-    fn on_solve_event(&mut self, event: clingo::SolveEvent<'_>, goon: &mut bool) -> bool {
-        match event {
-            clingo::SolveEvent::Model(model) => self.theory.borrow_mut().on_model(model),
-            clingo::SolveEvent::Statistics { step, akku } => {
-                self.theory.borrow_mut().on_statistics(step, akku)
-            }
-            _ => true,
+/// The configuration with or without descriptions
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ConfigurationListing {
+    Values(ConfigurationResult),
+    Verbose(ConfigurationDescription),
+}
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum TheoryTermResult {
+    Number {
+        number: i32,
+    },
+    Symbol {
+        name: String,
+    },
+    Function {
+        name: String,
+        arguments: Vec<TheoryTermResult>,
+    },
+    Tuple {
+        arguments: Vec<TheoryTermResult>,
+    },
+    List {
+        arguments: Vec<TheoryTermResult>,
+    },
+    Set {
+        arguments: Vec<TheoryTermResult>,
+    },
+}
+#[derive(Debug, Serialize)]
+pub struct TheoryElementResult {
+    tuple: Vec<TheoryTermResult>,
+    condition: Vec<i32>,
+    condition_id: i32,
+}
+#[derive(Debug, Serialize)]
+pub struct TheoryGuardResult {
+    operator: String,
+    term: TheoryTermResult,
+}
+#[derive(Debug, Serialize)]
+pub struct TheoryAtomResult {
+    term: TheoryTermResult,
+    elements: Vec<TheoryElementResult>,
+    guard: Option<TheoryGuardResult>,
+    literal: i32,
+    text: String,
+}
+/// A theory registered in a session under its name
+#[derive(Clone)]
+pub struct RegisteredTheory {
+    pub name: String,
+    statistics: Option<String>,
+    theory: Rc<RefCell<Box<dyn TheoryPlugin>>>,
+}
+/// The result of a finished search
+#[derive(Debug, Clone, Serialize)]
+pub struct SolveSummary {
+    /// Unknown if the search was interrupted before finding a model
+    pub satisfiable: Option<bool>,
+    pub exhausted: bool,
+    pub interrupted: bool,
+}
+/// The counters of `solving.solvers` in the progress of a search
+const SOLVER_COUNTERS: [&str; 3] = ["conflicts", "choices", "restarts"];
+
+/// The counters of `solving.solvers` in a statistics tree
+fn solver_counters(stats: &Statistics) -> Result<[f64; 3], ClingoError> {
+    let solving = stats.map_at(stats.root()?, "solving")?;
+    let solvers = stats.map_at(solving, "solvers")?;
+    let mut counters = [0.0; 3];
+    for (counter, name) in counters.iter_mut().zip(SOLVER_COUNTERS.iter()) {
+        *counter = stats.value_get(stats.map_at(solvers, name)?)?;
+    }
+    Ok(counters)
+}
+
+/// Counts the started searches to tell them apart
+static SEARCHES: AtomicU64 = AtomicU64::new(0);
+/// What is known about a running search
+///
+/// clingo only provides its statistics tree while no search is running,
+/// while solving the progress tracks the time, the models and their costs,
+/// the user statistics are known once the search has finished.
+/// The conflicts, choices and restarts of the solvers are read from the running control object
+/// whenever clingo provides its statistics and from the statistics of the finished step.
+pub struct SolveProgress {
+    search: u64,
+    timed_out: bool,
+    started: Instant,
+    finished: Option<Instant>,
+    models: u64,
+    costs: Vec<i64>,
+    /// conflicts, choices and restarts
+    counters: [f64; 3],
+    user_step: Option<StatisticsResult>,
+    user_accu: Option<StatisticsResult>,
+    result: Option<SolveSummary>,
+}
+impl SolveProgress {
+    fn new() -> SolveProgress {
+        SolveProgress {
+            search: SEARCHES.fetch_add(1, Ordering::SeqCst),
+            timed_out: false,
+            started: Instant::now(),
+            finished: None,
+            models: 0,
+            costs: vec![],
+            counters: [0.0; 3],
+            user_step: None,
+            user_accu: None,
+            result: None,
         }
     }
+    /// The progress under the key `progress`, it is not part of the clingo statistics
+    fn snapshot(&self) -> StatisticsResult {
+        let elapsed = self.finished.unwrap_or_else(Instant::now) - self.started;
+        let finished = if self.finished.is_some() { 1.0 } else { 0.0 };
+        let costs = self
+            .costs
+            .iter()
+            .map(|cost| StatisticsResult::Value(*cost as f64))
+            .collect();
+        let progress = vec![
+            (
+                "time".to_string(),
+                StatisticsResult::Value(elapsed.as_secs_f64()),
+            ),
+            (
+                "models".to_string(),
+                StatisticsResult::Value(self.models as f64),
+            ),
+            ("costs".to_string(), StatisticsResult::Array(costs)),
+            ("finished".to_string(), StatisticsResult::Value(finished)),
+        ];
+        let counters = SOLVER_COUNTERS
+            .iter()
+            .zip(self.counters.iter())
+            .map(|(name, value)| (name.to_string(), StatisticsResult::Value(*value)));
+        let progress = progress.into_iter().chain(counters).collect();
+        let mut snapshot = vec![("progress".to_string(), StatisticsResult::Map(progress))];
+        if let Some(user_step) = &self.user_step {
+            snapshot.push(("user_step".to_string(), user_step.clone()));
+        }
+        if let Some(user_accu) = &self.user_accu {
+            snapshot.push(("user_accu".to_string(), user_accu.clone()));
+        }
+        StatisticsResult::Map(snapshot)
+    }
 }
-pub struct ConEventHandler {
-    theory: Rc<RefCell<ConTheory>>,
+/// Forwards the solve events to all registered theories, adds the user statistics,
+/// tracks the progress of the search and records the models and the result of the run
+pub struct TheoryEventHandler {
+    theories: Vec<RegisteredTheory>,
+    user_statistics: Vec<(String, StatisticsResult)>,
+    progress: Arc<Mutex<SolveProgress>>,
+    runs: Option<Arc<Mutex<RunStore>>>,
 }
-impl clingo::SolveEventHandler for ConEventHandler {
-    fn on_solve_event(&mut self, event: clingo::SolveEvent<'_>, goon: &mut bool) -> bool {
+impl clingo::SolveEventHandler for TheoryEventHandler {
+    fn on_solve_event(&mut self, event: clingo::SolveEvent<'_>, _goon: &mut bool) -> bool {
         match event {
-            clingo::SolveEvent::Model(model) => self.theory.borrow_mut().on_model(model),
+            clingo::SolveEvent::Model(model) => {
+                let costs = model.cost().ok();
+                {
+                    let mut progress = self.progress.lock();
+                    progress.models += 1;
+                    if let Some(costs) = &costs {
+                        progress.costs = costs.clone();
+                    }
+                }
+                let ok = self.theories.iter().fold(true, |ok, registered| {
+                    registered.theory.borrow_mut().on_model(model) && ok
+                });
+                // the run is recorded even if the client never polls the model
+                if let Some(runs) = &self.runs {
+                    if let Ok(text) = model_text(model, &self.theories) {
+                        let text = String::from_utf8_lossy(&text).into_owned();
+                        runs.lock().add_model(text, costs.unwrap_or_default());
+                    }
+                }
+                ok
+            }
             clingo::SolveEvent::Statistics { step, akku } => {
-                self.theory.borrow_mut().on_statistics(step, akku)
+                let mut ok = self.theories.iter().fold(true, |ok, registered| {
+                    registered.theory.borrow_mut().on_statistics(step, akku) && ok
+                });
+                if !self.user_statistics.is_empty() {
+                    let user_statistics = StatisticsResult::Map(self.user_statistics.clone());
+                    ok = write_user_statistics(step, &user_statistics, false).is_ok()
+                        && write_user_statistics(akku, &user_statistics, true).is_ok()
+                        && ok;
+                }
+                let mut progress = self.progress.lock();
+                progress.finished = Some(Instant::now());
+                if let Ok(counters) = solver_counters(step) {
+                    progress.counters = counters;
+                }
+                progress.user_step = step.root().and_then(|key| parse_statistics(step, key)).ok();
+                progress.user_accu = akku.root().and_then(|key| parse_statistics(akku, key)).ok();
+                ok
+            }
+            clingo::SolveEvent::Finish(result) => {
+                let satisfiable = if result.contains(SolveResult::SATISFIABLE) {
+                    Some(true)
+                } else if result.contains(SolveResult::UNSATISFIABLE) {
+                    Some(false)
+                } else {
+                    None
+                };
+                let summary = SolveSummary {
+                    satisfiable,
+                    exhausted: result.contains(SolveResult::EXHAUSTED),
+                    interrupted: result.contains(SolveResult::INTERRUPTED),
+                };
+                self.progress.lock().result = Some(summary.clone());
+                if let Some(runs) = &self.runs {
+                    runs.lock().finish(summary);
+                }
+                true
             }
             _ => true,
         }
@@ -168,41 +537,31 @@ pub enum Solver {
     Control(ControlWrapper),
     SolveHandle(SolveHandleWrapper),
 }
-pub enum ControlWrapper {
-    DLTheory(Control, Rc<RefCell<DLTheory>>),
-    // DLTheory(Control, Rc<RefCell<DLTheory>>, Rc<RefCell<Library>>),
-    ConTheory(Control, Rc<RefCell<ConTheory>>),
-    NoTheory(Control),
+pub struct ControlWrapper {
+    ctl: Control,
+    theories: Vec<RegisteredTheory>,
+    user_statistics: Vec<(String, StatisticsResult)>,
+    pending: Vec<Statement>,
+    runs: Option<Arc<Mutex<RunStore>>>,
+    /// The atoms of the ids of the added ground programs
+    ground_atoms: AtomIds,
 }
 impl ControlWrapper {
     fn configuration(&mut self) -> Result<&Configuration, ClingoError> {
-        match self {
-            ControlWrapper::DLTheory(ctl, _) => ctl.configuration(),
-            ControlWrapper::ConTheory(ctl, _) => ctl.configuration(),
-            ControlWrapper::NoTheory(ctl) => ctl.configuration(),
-        }
+        self.ctl.configuration()
     }
     fn configuration_mut(&mut self) -> Result<&mut Configuration, ClingoError> {
-        match self {
-            ControlWrapper::DLTheory(ctl, _) => ctl.configuration_mut(),
-            ControlWrapper::ConTheory(ctl, _) => ctl.configuration_mut(),
-            ControlWrapper::NoTheory(ctl) => ctl.configuration_mut(),
-        }
+        self.ctl.configuration_mut()
     }
     fn statistics(&mut self) -> Result<&Statistics, ClingoError> {
-        match self {
-            ControlWrapper::DLTheory(ctl, _) => ctl.statistics(),
-            ControlWrapper::ConTheory(ctl, _) => ctl.statistics(),
-            ControlWrapper::NoTheory(ctl) => ctl.statistics(),
-        }
+        self.ctl.statistics()
+    }
+    fn theory_atoms(&self) -> Result<&TheoryAtoms, ClingoError> {
+        self.ctl.theory_atoms()
     }
 
     pub fn symbolic_atoms<'a>(&self) -> Result<&'a SymbolicAtoms, ClingoError> {
-        match self {
-            ControlWrapper::DLTheory(ctl, _) => ctl.symbolic_atoms(),
-            ControlWrapper::ConTheory(ctl, _) => ctl.symbolic_atoms(),
-            ControlWrapper::NoTheory(ctl) => ctl.symbolic_atoms(),
-        }
+        self.ctl.symbolic_atoms()
     }
     pub fn assign_external(
         &mut self,
@@ -216,13 +575,29 @@ impl ControlWrapper {
             .find(|e| e.symbol().unwrap() == *symbol)
             .ok_or_else(|| ServerError::InternalError("external symbol not found".to_string()))?;
         let atm = item.literal()?;
-        match self {
-            ControlWrapper::DLTheory(ctl, _) => ctl.assign_external(atm, *truth_value),
-            ControlWrapper::ConTheory(ctl, _) => ctl.assign_external(atm, *truth_value),
-            ControlWrapper::NoTheory(ctl) => ctl.assign_external(atm, *truth_value),
-        }?;
+        self.ctl.assign_external(atm, *truth_value)?;
         Ok(())
     }
+    fn backend(&mut self) -> Result<Backend, ClingoError> {
+        self.ctl.backend()
+    }
+    fn register_observer(&mut self, observer: GroundProgramRecorder) -> Result<(), ClingoError> {
+        self.ctl.register_observer(observer, false)
+    }
+    pub fn add_facts(&mut self, facts: &[Symbol]) -> Result<(), ServerError> {
+        let mut backend = self.backend()?;
+        // every symbol becomes an atom with an empty body
+        for symbol in facts {
+            let atm = backend.add_atom(Some(*symbol))?;
+            backend.rule(false, &[atm], &[])?;
+        }
+        Ok(())
+    }
+    pub fn add_ground_program(&mut self, program: &GroundProgram) -> Result<(), ServerError> {
+        let program = program.validate()?;
+        let mut backend = self.ctl.backend()?;
+        program.add_to_backend(&mut backend, &mut self.ground_atoms)
+    }
     pub fn release_external(&mut self, symbol: &Symbol) -> Result<(), ServerError> {
         // get the program literal corresponding to the external atom
         let atoms = self.symbolic_atoms()?;
@@ -231,18 +606,35 @@ impl ControlWrapper {
             .find(|e| e.symbol().unwrap() == *symbol)
             .ok_or_else(|| ServerError::InternalError("external symbol not found".to_string()))?;
         let atm = item.literal()?;
-        match self {
-            ControlWrapper::DLTheory(ctl, _) => ctl.release_external(atm),
-            ControlWrapper::ConTheory(ctl, _) => ctl.release_external(atm),
-            ControlWrapper::NoTheory(ctl) => ctl.release_external(atm),
-        }?;
+        self.ctl.release_external(atm)?;
         Ok(())
     }
 }
-pub enum SolveHandleWrapper {
-    DLTheory(DLSolveHandle, Rc<RefCell<DLTheory>>),
-    ConTheory(ConSolveHandle, Rc<RefCell<ConTheory>>),
-    NoTheory(SolveHandle),
+pub struct SolveHandleWrapper {
+    handle: GenericSolveHandle<DefaultCtx, TheoryEventHandler>,
+    theories: Vec<RegisteredTheory>,
+    user_statistics: Vec<(String, StatisticsResult)>,
+    pending: Vec<Statement>,
+    runs: Option<Arc<Mutex<RunStore>>>,
+    ground_atoms: AtomIds,
+    progress: Arc<Mutex<SolveProgress>>,
+    control: RunningControl,
+}
+impl SolveHandleWrapper {
+    /// The progress of the search with the current counters of the solvers
+    fn snapshot(&self) -> StatisticsResult {
+        let mut progress = self.progress.lock();
+        if progress.finished.is_none() {
+            if let Some(counters) = self
+                .control
+                .statistics()
+                .and_then(|stats| solver_counters(stats).ok())
+            {
+                progress.counters = counters;
+            }
+        }
+        progress.snapshot()
+    }
 }
 impl Default for Solver {
     fn default() -> Self {
@@ -258,9 +650,25 @@ impl Solver {
 unsafe impl Send for Solver {}
 impl Solver {
     pub fn create(&mut self, arguments: std::vec::Vec<String>) -> Result<(), ServerError> {
+        let create_control = |arguments: Vec<String>| {
+            let text = arguments.join(" ");
+            control(arguments).map_err(|e| {
+                ServerError::InternalError(format!(
+                    "Solver::create failed! Invalid arguments {}. {}",
+                    text, e
+                ))
+            })
+        };
         match self {
             Solver::None => {
-                *self = Solver::Control(ControlWrapper::NoTheory(control(arguments)?));
+                *self = Solver::Control(ControlWrapper {
+                    ctl: create_control(arguments)?,
+                    theories: vec![],
+                    user_statistics: vec![],
+                    pending: vec![],
+                    runs: None,
+                    ground_atoms: AtomIds::new(),
+                });
             }
             Solver::SolveHandle(_) => {
                 return Err(ServerError::InternalError(
@@ -268,125 +676,103 @@ impl Solver {
                 ))
             }
             Solver::Control(_) => {
-                let ctl = control(arguments)?;
-                *self = Solver::Control(ControlWrapper::NoTheory(ctl));
+                let ctl = create_control(arguments)?;
+                *self = Solver::Control(ControlWrapper {
+                    ctl,
+                    theories: vec![],
+                    user_statistics: vec![],
+                    pending: vec![],
+                    runs: None,
+                    ground_atoms: AtomIds::new(),
+                });
             }
         }
         Ok(())
     }
-    pub fn register_dl_theory(&mut self) -> Result<(), ServerError> {
-        let x = self.take();
-        match x {
-            Solver::None => {
-                return Err(ServerError::InternalError(
-                    "Solver::register_dl_theory failed! No control object.".to_string(),
-                ))
-            }
-            Solver::SolveHandle(_) => {
-                *self = x;
-                return Err(ServerError::InternalError(
-                    "Solver::register_dl_theory failed! Solver has been already started."
-                        .to_string(),
-                ));
-            }
-            Solver::Control(ControlWrapper::DLTheory(mut ctl, _)) => {
-                let library_path = "clingodl";
-                println!("Loading add() from {}", library_path);
-                //Loads the library and gets a symbol (casting the function pointer so it has the desired signature)
-                let lib = unsafe { libloading::Library::new(library_path) }?;
-                let create: libloading::Symbol<unsafe fn() -> DLTheory> =
-                    unsafe { lib.get(b"create") }?;
-                let mut dl_theory = unsafe { create() };
-                dl_theory.register(&mut ctl);
-
-                *self = Solver::Control(ControlWrapper::DLTheory(
-                    ctl,
-                    Rc::new(RefCell::new(dl_theory)),
-                ));
-            }
-            Solver::Control(ControlWrapper::ConTheory(mut ctl, _)) => {
-                let library_path = "clingodl";
-                println!("Loading add() from {}", library_path);
-                //Loads the library and gets a symbol (casting the function pointer so it has the desired signature)
-                let lib = unsafe { libloading::Library::new(library_path) }?;
-                let create: libloading::Symbol<unsafe fn() -> DLTheory> =
-                    unsafe { lib.get(b"create") }?;
-                let mut dl_theory = unsafe { create() };
-                dl_theory.register(&mut ctl);
-
-                *self = Solver::Control(ControlWrapper::DLTheory(
-                    ctl,
-                    Rc::new(RefCell::new(dl_theory)),
-                ));
-            }
-            Solver::Control(ControlWrapper::NoTheory(mut ctl)) => {
-                let mut dl_theory = DLTheory::create();
-                dl_theory.register(&mut ctl);
-                *self = Solver::Control(ControlWrapper::DLTheory(
-                    ctl,
-                    Rc::new(RefCell::new(dl_theory)),
-                ));
+    pub fn register_observer(
+        &mut self,
+        observer: GroundProgramRecorder,
+    ) -> Result<(), ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::register_observer failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::register_observer failed! Solver has been already started.".to_string(),
+            )),
+            Solver::Control(ctl) => {
+                ctl.register_observer(observer)?;
+                Ok(())
             }
-        };
-        Ok(())
+        }
     }
-    pub fn register_con_theory(&mut self) -> Result<(), ServerError> {
-        let x = self.take();
-        match x {
-            Solver::None => {
-                return Err(ServerError::InternalError(
-                    "Solver::register_con_theory failed! No control object.".to_string(),
-                ))
-            }
-            Solver::SolveHandle(_) => {
-                *self = x;
-                return Err(ServerError::InternalError(
-                    "Solver::register_con_theory failed! Solver has been already started."
-                        .to_string(),
-                ));
-            }
-            Solver::Control(ControlWrapper::DLTheory(mut ctl, _)) => {
-                let library_path = "clingcon";
-                println!("Loading add() from {}", library_path);
-                //Loads the library and gets a symbol (casting the function pointer so it has the desired signature)
-                let lib = unsafe { libloading::Library::new(library_path) }?;
-                let create: libloading::Symbol<unsafe fn() -> ConTheory> =
-                    unsafe { lib.get(b"create") }?;
-                let mut con_theory = unsafe { create() };
-                con_theory.register(&mut ctl);
-
-                *self = Solver::Control(ControlWrapper::ConTheory(
-                    ctl,
-                    Rc::new(RefCell::new(con_theory)),
-                ));
-            }
-            Solver::Control(ControlWrapper::ConTheory(mut ctl, _)) => {
-                let library_path = "clingcon";
-                println!("Loading add() from {}", library_path);
-                //Loads the library and gets a symbol (casting the function pointer so it has the desired signature)
-
-                let lib = unsafe { libloading::Library::new(library_path) }?;
-                let create: libloading::Symbol<unsafe fn() -> ConTheory> =
-                    unsafe { lib.get(b"create") }?;
-                let mut con_theory = unsafe { create() };
-
-                con_theory.register(&mut ctl);
-
-                *self = Solver::Control(ControlWrapper::ConTheory(
-                    ctl,
-                    Rc::new(RefCell::new(con_theory)),
-                ));
+    /// Record the models and the result of the following searches in the run store
+    pub fn record_runs(&mut self, runs: Arc<Mutex<RunStore>>) -> Result<(), ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::record_runs failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::record_runs failed! Solver has been already started.".to_string(),
+            )),
+            Solver::Control(ctl) => {
+                ctl.runs = Some(runs);
+                Ok(())
             }
-            Solver::Control(ControlWrapper::NoTheory(mut ctl)) => {
-                let mut con_theory = ConTheory::create();
-                con_theory.register(&mut ctl);
-                *self = Solver::Control(ControlWrapper::ConTheory(
-                    ctl,
-                    Rc::new(RefCell::new(con_theory)),
-                ));
+        }
+    }
+    /// Register an additional theory, the error messages refer to the theory by its name
+    pub fn register_theory(
+        &mut self,
+        name: &str,
+        instance: TheoryInstance,
+    ) -> Result<(), ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(format!(
+                "Solver::register_{}_theory failed! No control object.",
+                name
+            ))),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(format!(
+                "Solver::register_{}_theory failed! Solver has been already started.",
+                name
+            ))),
+            Solver::Control(ctl) => {
+                if ctl
+                    .theories
+                    .iter()
+                    .any(|registered| registered.name == name)
+                {
+                    return Err(ServerError::InternalError(format!(
+                        "Solver::register_{}_theory failed! Theory {} already registered.",
+                        name, name
+                    )));
+                }
+                let TheoryInstance {
+                    mut theory,
+                    statistics,
+                } = instance;
+                if let Some(key) = &statistics {
+                    if ctl.user_statistics.iter().any(|(name, _)| name == key) {
+                        return Err(ServerError::InternalError(format!(
+                            "Solver::register_{}_theory failed! The user statistics already contain an entry {}.",
+                            name, key
+                        )));
+                    }
+                }
+                if !theory.register(&mut ctl.ctl) {
+                    return Err(clingo_error(&format!(
+                        "Solver::register_{}_theory failed!",
+                        name
+                    )));
+                }
+                ctl.theories.push(RegisteredTheory {
+                    name: name.to_string(),
+                    statistics,
+                    theory: Rc::new(RefCell::new(theory)),
+                });
+                Ok(())
             }
-        };
-        Ok(())
+        }
     }
     pub fn close(&mut self) -> Result<(), ServerError> {
         let x = self.take();
@@ -402,14 +788,23 @@ impl Solver {
                     "Solver::close failed! Solver is not running.".to_string(),
                 ));
             }
-            Solver::SolveHandle(SolveHandleWrapper::DLTheory(handle, dl_theory)) => {
-                *self = Solver::Control(ControlWrapper::DLTheory(handle.close()?, dl_theory));
-            }
-            Solver::SolveHandle(SolveHandleWrapper::ConTheory(handle, con_theory)) => {
-                *self = Solver::Control(ControlWrapper::ConTheory(handle.close()?, con_theory));
-            }
-            Solver::SolveHandle(SolveHandleWrapper::NoTheory(handle)) => {
-                *self = Solver::Control(ControlWrapper::NoTheory(handle.close()?));
+            Solver::SolveHandle(SolveHandleWrapper {
+                handle,
+                theories,
+                user_statistics,
+                pending,
+                runs,
+                ground_atoms,
+                ..
+            }) => {
+                *self = Solver::Control(ControlWrapper {
+                    ctl: handle.close()?,
+                    theories,
+                    user_statistics,
+                    pending,
+                    runs,
+                    ground_atoms,
+                });
             }
         };
         Ok(())
@@ -429,43 +824,50 @@ impl Solver {
             Solver::SolveHandle(_) => {
                 *self = x;
                 return Err(ServerError::InternalError(
-                    "Solver::solve failed! DLSolving has already started.".to_string(),
+                    "Solver::solve failed! Solving has already started.".to_string(),
                 ));
             }
-            Solver::Control(ControlWrapper::DLTheory(ctl, dl_theory)) => {
-                let on_model = DLEventHandler {
-                    theory: dl_theory.clone(),
+            Solver::Control(ControlWrapper {
+                mut ctl,
+                theories,
+                user_statistics,
+                pending,
+                runs,
+                ground_atoms,
+            }) => {
+                let control = RunningControl::new(&mut ctl);
+                let progress = Arc::new(Mutex::new(SolveProgress::new()));
+                let event_handler = TheoryEventHandler {
+                    theories: theories.clone(),
+                    user_statistics: user_statistics.clone(),
+                    progress: progress.clone(),
+                    runs: runs.clone(),
                 };
 
-                *self = Solver::SolveHandle(SolveHandleWrapper::DLTheory(
-                    ctl.solve_with_event_handler(mode, assumptions, on_model)?,
-                    dl_theory,
-                ));
-            }
-            Solver::Control(ControlWrapper::ConTheory(ctl, con_theory)) => {
-                let on_model = ConEventHandler {
-                    theory: con_theory.clone(),
-                };
-
-                *self = Solver::SolveHandle(SolveHandleWrapper::ConTheory(
-                    ctl.solve_with_event_handler(mode, assumptions, on_model)?,
-                    con_theory,
-                ));
-            }
-            Solver::Control(ControlWrapper::NoTheory(ctl)) => {
-                *self = Solver::SolveHandle(SolveHandleWrapper::NoTheory(
-                    ctl.solve(mode, assumptions)?,
-                ));
+                *self = Solver::SolveHandle(SolveHandleWrapper {
+                    handle: ctl.solve_with_event_handler(mode, assumptions, event_handler)?,
+                    theories,
+                    user_statistics,
+                    pending,
+                    runs,
+                    ground_atoms,
+                    progress,
+                    control,
+                });
             }
         };
         Ok(())
     }
-    pub fn add(
-        &mut self,
-        name: &str,
-        parameters: &[&str],
-        program: &str,
-    ) -> Result<(), ServerError> {
+    /// Parse the program in a file, the statements are added to the control object with the next grounding
+    ///
+    /// The statements are rewritten by the registered theories.
+    pub fn add_file(&mut self, path: &Path) -> Result<(), ServerError> {
+        let path = path.to_str().ok_or_else(|| {
+            ServerError::InternalError(format!(
+                "Solver::add failed! Invalid file name {}.",
+                path.display()
+            ))
+        })?;
         match self {
             Solver::None => {
                 return Err(ServerError::InternalError(
@@ -477,31 +879,87 @@ impl Solver {
                     "Solver::add failed! Solver has been already started.".to_string(),
                 ))
             }
-            Solver::Control(ControlWrapper::DLTheory(ctl, dl_theory)) => {
-                let mut bld = ast::ProgramBuilder::from(ctl)?;
-                let mut rewriter = Rewriter {
-                    builder: &mut bld,
-                    theory: dl_theory.clone(),
-                };
-                // rewrite the program
-                clingo::ast::parse_string_with_statement_handler(program, &mut rewriter)?;
-            }
-            Solver::Control(ControlWrapper::ConTheory(ctl, con_theory)) => {
-                let mut bld = ast::ProgramBuilder::from(ctl)?;
-                let mut rewriter = ConRewriter {
-                    builder: &mut bld,
-                    theory: con_theory.clone(),
-                };
+            Solver::Control(ControlWrapper {
+                theories, pending, ..
+            }) => {
+                let path = CString::new(path).map_err(|_| {
+                    ServerError::InternalError(
+                        "Solver::add failed! The file name contains a nul byte.".to_string(),
+                    )
+                })?;
+                let files = [path.as_ptr()];
                 // rewrite the program
-                clingo::ast::parse_string_with_statement_handler(program, &mut rewriter)?;
-            }
-            Solver::Control(ControlWrapper::NoTheory(ctl)) => {
-                ctl.add(name, parameters, program)?;
+                let statements =
+                    rewrite_program(theories, "Solver::add failed!", |callback, data| unsafe {
+                        clingo_ast_parse_files(
+                            files.as_ptr(),
+                            files.len(),
+                            Some(callback),
+                            data,
+                            ptr::null_mut(),
+                            None,
+                            ptr::null_mut(),
+                            20,
+                        )
+                    })?;
+                pending.extend(statements);
             }
         };
         Ok(())
     }
-    pub fn ground(&mut self, parts: &[Part]) -> Result<(), ServerError> {
+    /// Load a ground program in aspif format, theory statements are kept for the registered theories
+    pub fn load_aspif(&mut self, path: &Path) -> Result<(), ServerError> {
+        let path = path.to_str().ok_or_else(|| {
+            ServerError::InternalError(format!(
+                "Solver::load_aspif failed! Invalid file name {}.",
+                path.display()
+            ))
+        })?;
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::load_aspif failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::load_aspif failed! Solver has been already started.".to_string(),
+            )),
+            // clingo detects the aspif format by its header
+            Solver::Control(ControlWrapper { ctl, .. }) => {
+                ctl.load(path)?;
+                Ok(())
+            }
+        }
+    }
+    pub fn add_facts(&mut self, facts: &[Symbol]) -> Result<(), ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::add_facts failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::add_facts failed! Solver has been already started.".to_string(),
+            )),
+            Solver::Control(ctl) => ctl.add_facts(facts),
+        }
+    }
+    pub fn add_ground_program(&mut self, program: &GroundProgram) -> Result<(), ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::add_ground_program failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::add_ground_program failed! Solver has been already started.".to_string(),
+            )),
+            Solver::Control(ctl) => ctl.add_ground_program(program),
+        }
+    }
+    /// Ground the parts
+    ///
+    /// The statements of the programs added since the last grounding are passed to the control
+    /// object first, the constants replace their `#const` definitions as `[override]` definitions.
+    pub fn ground(
+        &mut self,
+        parts: &[Part],
+        constants: &[(String, Symbol)],
+    ) -> Result<(), ServerError> {
         match self {
             Solver::None => {
                 return Err(ServerError::InternalError(
@@ -513,16 +971,19 @@ impl Solver {
                     "Solver::ground failed! Solver has been already started.".to_string(),
                 ))
             }
-            Solver::Control(ControlWrapper::DLTheory(ctl, dl_theory)) => {
-                ctl.ground(parts)?;
-                dl_theory.borrow_mut().prepare(ctl);
-            }
-            Solver::Control(ControlWrapper::ConTheory(ctl, con_theory)) => {
-                ctl.ground(parts)?;
-                con_theory.borrow_mut().prepare(ctl);
-            }
-            Solver::Control(ControlWrapper::NoTheory(ctl)) => {
+            Solver::Control(ControlWrapper {
+                ctl,
+                theories,
+                pending,
+                ..
+            }) => {
+                add_statements(ctl, mem::take(pending), constants)?;
                 ctl.ground(parts)?;
+                for registered in theories {
+                    if !registered.theory.borrow_mut().prepare(ctl) {
+                        return Err(clingo_error("Solver::ground failed!"));
+                    }
+                }
             }
         };
         Ok(())
@@ -557,9 +1018,7 @@ impl Solver {
             Solver::None => Err(ServerError::InternalError(
                 "Solver::statistics failed! No control object.".to_string(),
             )),
-            Solver::SolveHandle(_) => Err(ServerError::InternalError(
-                "Solver::statistics failed! Solving has already started.".to_string(),
-            )),
+            Solver::SolveHandle(wrapper) => Ok(wrapper.snapshot()),
             Solver::Control(ctl) => {
                 let stats = ctl.statistics()?;
                 let root_key = stats.root()?;
@@ -568,7 +1027,171 @@ impl Solver {
             }
         }
     }
-    pub fn configuration(&mut self) -> Result<ConfigurationResult, ServerError> {
+    /// The costs of the last model of the search, empty if not solving or without optimization
+    pub fn costs(&self) -> Vec<i64> {
+        match self {
+            Solver::SolveHandle(SolveHandleWrapper { progress, .. }) => {
+                progress.lock().costs.clone()
+            }
+            _ => vec![],
+        }
+    }
+    /// The result of the search once it is finished
+    pub fn solve_summary(&self) -> Option<SolveSummary> {
+        match self {
+            Solver::SolveHandle(SolveHandleWrapper { progress, .. }) => {
+                progress.lock().result.clone()
+            }
+            _ => None,
+        }
+    }
+    /// Add or update entries of the user statistics of the session
+    ///
+    /// They are written to `user_step` and added to `user_accu` at the end of each search.
+    /// Entries keep their type and can not replace the statistics of a registered theory.
+    pub fn add_user_statistics(
+        &mut self,
+        entries: Vec<(String, StatisticsResult)>,
+    ) -> Result<(), ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::add_user_statistics failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::add_user_statistics failed! Solving has already started.".to_string(),
+            )),
+            Solver::Control(ctl) => {
+                let error = |msg: String| {
+                    ServerError::InternalError(format!(
+                        "Solver::add_user_statistics failed! {}",
+                        msg
+                    ))
+                };
+                // the entries of the theories are written by the theories themselves
+                for (name, _) in &entries {
+                    if let Some(registered) = ctl
+                        .theories
+                        .iter()
+                        .find(|registered| registered.statistics.as_ref() == Some(name))
+                    {
+                        return Err(error(format!(
+                            "Entry {} is reserved for the statistics of theory {}.",
+                            name, registered.name
+                        )));
+                    }
+                }
+                let entries = StatisticsResult::Map(entries);
+                let mut user_statistics =
+                    StatisticsResult::Map(mem::take(&mut ctl.user_statistics));
+                let checked = user_statistics.check_merge(&entries, "");
+                if checked.is_ok() {
+                    user_statistics.merge(entries);
+                }
+                if let StatisticsResult::Map(array) = user_statistics {
+                    ctl.user_statistics = array;
+                }
+                checked.map_err(error)
+            }
+        }
+    }
+    /// The subtrees of the statistics at the given dotted paths like `summary.times`
+    ///
+    /// Only the requested subtrees are traversed, array elements are selected by their index.
+    pub fn statistics_at(&mut self, paths: &[String]) -> Result<StatisticsResult, ServerError> {
+        let unknown_path = |path: &str| {
+            ServerError::InternalError(format!(
+                "Solver::statistics failed! Unknown statistics path {}.",
+                path
+            ))
+        };
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::statistics failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(wrapper) => {
+                let snapshot = wrapper.snapshot();
+                let mut array = vec![];
+                for path in paths {
+                    let subtree = snapshot.at(path).ok_or_else(|| {
+                        ServerError::InternalError(format!(
+                            "Solver::statistics failed! Statistics path {} is not available while solving.",
+                            path
+                        ))
+                    })?;
+                    array.push((path.clone(), subtree.clone()));
+                }
+                Ok(StatisticsResult::Map(array))
+            }
+            Solver::Control(ctl) => {
+                let stats = ctl.statistics()?;
+                let root_key = stats.root()?;
+                let mut array = vec![];
+                for path in paths {
+                    let key =
+                        statistics_key(stats, root_key, path)?.ok_or_else(|| unknown_path(path))?;
+                    array.push((path.clone(), parse_statistics(stats, key)?));
+                }
+                Ok(StatisticsResult::Map(array))
+            }
+        }
+    }
+    /// The statistics the registered theories add to the per step and the accumulated user statistics
+    pub fn theory_statistics(
+        &mut self,
+        view: StatisticsView,
+    ) -> Result<StatisticsResult, ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::theory_statistics failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::theory_statistics failed! Solving has already started.".to_string(),
+            )),
+            Solver::Control(ctl) => {
+                let keys: Vec<String> = ctl
+                    .theories
+                    .iter()
+                    .filter_map(|registered| registered.statistics.clone())
+                    .collect();
+                let stats = ctl.statistics()?;
+                let root_key = stats.root()?;
+                let mut views = vec![];
+                if view != StatisticsView::Accu {
+                    let step = parse_user_statistics(stats, root_key, "user_step", &keys)?;
+                    views.push(("step".to_string(), step));
+                }
+                if view != StatisticsView::Step {
+                    let accu = parse_user_statistics(stats, root_key, "user_accu", &keys)?;
+                    views.push(("accu".to_string(), accu));
+                }
+                Ok(StatisticsResult::Map(views))
+            }
+        }
+    }
+    pub fn theory_atoms(&mut self) -> Result<Vec<TheoryAtomResult>, ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::theory_atoms failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::theory_atoms failed! Solving has already started.".to_string(),
+            )),
+            Solver::Control(ctl) => {
+                let atoms = ctl.theory_atoms()?;
+                let mut atoms_result = Vec::with_capacity(atoms.size()?);
+                for atom in atoms.iter() {
+                    atoms_result.push(parse_theory_atom(atoms, atom)?);
+                }
+                Ok(atoms_result)
+            }
+        }
+    }
+    /// The configuration at a dotted path like `solve.models`, the whole configuration if no path is given
+    pub fn configuration(
+        &mut self,
+        path: Option<&str>,
+        verbose: bool,
+    ) -> Result<ConfigurationListing, ServerError> {
         match self {
             Solver::None => Err(ServerError::InternalError(
                 "Solver::configuration failed! No control object.".to_string(),
@@ -579,8 +1202,61 @@ impl Solver {
             Solver::Control(ctl) => {
                 let conf = ctl.configuration()?;
                 let root_key = conf.root()?;
-                let conf_result = parse_configuration(conf, root_key)?;
-                Ok(conf_result)
+                let key = match path {
+                    Some(path) => configuration_key(conf, root_key, path)?.ok_or_else(|| {
+                        ServerError::InternalError(format!(
+                            "Solver::configuration failed! Unknown configuration key {}.",
+                            path
+                        ))
+                    })?,
+                    None => root_key,
+                };
+                if verbose {
+                    Ok(ConfigurationListing::Verbose(describe_configuration(
+                        conf, key,
+                    )?))
+                } else {
+                    Ok(ConfigurationListing::Values(parse_configuration(
+                        conf, key,
+                    )?))
+                }
+            }
+        }
+    }
+    /// Set a single configuration value at a dotted path
+    pub fn set_configuration_value(&mut self, path: &str, value: &str) -> Result<(), ServerError> {
+        match self {
+            Solver::None => Err(ServerError::InternalError(
+                "Solver::set_configuration failed! No control object.".to_string(),
+            )),
+            Solver::SolveHandle(_) => Err(ServerError::InternalError(
+                "Solver::set_configuration failed! Solving has already started.".to_string(),
+            )),
+            Solver::Control(ctl) => {
+                let conf = ctl.configuration_mut()?;
+                let root_key = conf.root()?;
+                let key = configuration_key(conf, root_key, path)?.ok_or_else(|| {
+                    ServerError::InternalError(format!(
+                        "Solver::set_configuration failed! Unknown configuration key {}.",
+                        path
+                    ))
+                })?;
+                if !conf
+                    .configuration_type(key)?
+                    .contains(ConfigurationType::VALUE)
+                {
+                    return Err(ServerError::InternalError(format!(
+                        "Solver::set_configuration failed! Configuration key {} is not a value.",
+                        path
+                    )));
+                }
+                conf.value_set(key, value).map_err(|e| {
+                    ServerError::InternalError(format!(
+                        "Solver::set_configuration failed! Invalid value {} for configuration key {}. {}",
+                        value, path, e
+                    ))
+                })?;
+                Ok(())
             }
         }
     }
@@ -598,7 +1274,7 @@ impl Solver {
             Solver::Control(ctl) => {
                 let conf = ctl.configuration_mut()?;
                 let root_key = conf.root()?;
-                __set_conf(conf, new_conf, root_key)?;
+                __set_conf(conf, new_conf, root_key, "")?;
                 let conf_result = parse_configuration(conf, root_key)?;
                 Ok(conf_result)
             }
@@ -647,21 +1323,12 @@ impl Solver {
             Solver::Control(_) => Err(ServerError::InternalError(
                 "Solver::model failed! Solving has not yet started.".to_string(),
             )),
-            Solver::SolveHandle(SolveHandleWrapper::DLTheory(handle, dl_theory)) => {
+            Solver::SolveHandle(SolveHandleWrapper {
+                handle, theories, ..
+            }) => {
                 if handle.wait(Duration::ZERO) {
                     match handle.model_mut() {
-                        Ok(Some(model)) => {
-                            // dl_theory.on_model(model);
-                            let mut buf = vec![];
-                            write_model(model, &mut buf)?;
-                            // TODO rewrite write_dl_theory_assignment to use boxed iterator
-                            write_dl_theory_assignment(
-                                dl_theory.borrow_mut().assignment(model.thread_id()?),
-                                &mut buf,
-                            )?;
-
-                            Ok(ModelResult::Model(buf))
-                        }
+                        Ok(Some(model)) => Ok(ModelResult::Model(model_text(model, theories)?)),
                         Ok(None) => Ok(ModelResult::Done),
                         Err(e) => Err(e.into()),
                     }
@@ -669,43 +1336,41 @@ impl Solver {
                     Ok(ModelResult::Running)
                 }
             }
-            Solver::SolveHandle(SolveHandleWrapper::ConTheory(handle, con_theory)) => {
-                if handle.wait(Duration::ZERO) {
-                    match handle.model_mut() {
-                        Ok(Some(model)) => {
-                            let mut buf = vec![];
-                            write_model(model, &mut buf)?;
-                            // TODO rewrite write_dl_theory_assignment to use boxed iterator
-                            write_con_theory_assignment(
-                                con_theory.borrow_mut().assignment(model.thread_id()?),
-                                &mut buf,
-                            )?;
-
-                            Ok(ModelResult::Model(buf))
-                        }
-                        Ok(None) => Ok(ModelResult::Done),
-                        Err(e) => Err(e.into()),
-                    }
-                } else {
-                    Ok(ModelResult::Running)
-                }
+        }
+    }
+    /// The id of the running search, none if solving has not started
+    pub fn search(&self) -> Option<u64> {
+        match self {
+            Solver::SolveHandle(SolveHandleWrapper { progress, .. }) => {
+                Some(progress.lock().search)
             }
-            Solver::SolveHandle(SolveHandleWrapper::NoTheory(handle)) => {
-                if handle.wait(Duration::ZERO) {
-                    match handle.model_mut() {
-                        Ok(Some(model)) => {
-                            let mut buf = vec![];
-                            write_model(model, &mut buf)?;
-                            Ok(ModelResult::Model(buf))
-                        }
-                        Ok(None) => Ok(ModelResult::Done),
-                        Err(e) => Err(e.into()),
-                    }
-                } else {
-                    Ok(ModelResult::Running)
+            _ => None,
+        }
+    }
+    /// Cancel the search if it runs longer than `timeout`
+    ///
+    /// A search cancelled by the timeout keeps failing with the timeout error.
+    pub fn check_timeout(&mut self, timeout: Duration) -> Result<(), ServerError> {
+        if let Solver::SolveHandle(SolveHandleWrapper {
+            handle, progress, ..
+        }) = self
+        {
+            let exceeded = {
+                let mut progress = progress.lock();
+                if progress.finished.is_none() && progress.started.elapsed() >= timeout {
+                    progress.timed_out = true;
                 }
+                progress.timed_out
+            };
+            if exceeded {
+                handle.cancel()?;
+                return Err(ServerError::InternalError(format!(
+                    "Solver::model failed! Search cancelled after the solve timeout of {} seconds.",
+                    timeout.as_secs()
+                )));
             }
         }
+        Ok(())
     }
     pub fn resume(&mut self) -> Result<(), ServerError> {
         match self {
@@ -715,15 +1380,7 @@ impl Solver {
             Solver::Control(_) => Err(ServerError::InternalError(
                 "Solver::resume failed! Solver has not yet started.".to_string(),
             )),
-            Solver::SolveHandle(SolveHandleWrapper::DLTheory(handle, _)) => {
-                handle.resume()?;
-                Ok(())
-            }
-            Solver::SolveHandle(SolveHandleWrapper::ConTheory(handle, _)) => {
-                handle.resume()?;
-                Ok(())
-            }
-            Solver::SolveHandle(SolveHandleWrapper::NoTheory(handle)) => {
+            Solver::SolveHandle(SolveHandleWrapper { handle, .. }) => {
                 handle.resume()?;
                 Ok(())
             }
@@ -743,20 +1400,24 @@ pub fn write_model(model: &Model, mut out: impl io::Write) -> Result<(), io::Err
     }
     Ok(())
 }
-fn write_dl_theory_assignment<'a>(
-    dl_theory_assignment: Box<dyn Iterator<Item = (Symbol, clingo::theory::TheoryValue)> + 'a>,
-    mut out: impl io::Write,
-) -> Result<(), io::Error> {
-    for (symbol, theory_value) in dl_theory_assignment {
-        writeln!(out, "{}={}", symbol, theory_value)?;
+/// The shown symbols of a model followed by the assignments of the registered theories
+fn model_text(model: &Model, theories: &[RegisteredTheory]) -> Result<Vec<u8>, ServerError> {
+    let mut buf = vec![];
+    write_model(model, &mut buf)?;
+    let thread_id = model.thread_id()?.get();
+    for registered in theories {
+        write_theory_assignment(
+            &registered.theory.borrow_mut().assignment(thread_id),
+            &mut buf,
+        )?;
     }
-    Ok(())
+    Ok(buf)
 }
-pub fn write_con_theory_assignment<'a>(
-    con_theory_assignment: Box<dyn Iterator<Item = (Symbol, clingo::theory::TheoryValue)> + 'a>,
+fn write_theory_assignment(
+    theory_assignment: &[(String, String)],
     mut out: impl io::Write,
 ) -> Result<(), io::Error> {
-    for (symbol, theory_value) in con_theory_assignment {
+    for (symbol, theory_value) in theory_assignment {
         writeln!(out, "{}={}", symbol, theory_value)?;
     }
     Ok(())
@@ -790,7 +1451,7 @@ impl Read for ModelStream {
 }
 
 use rocket::request::{self, FromRequest, Request};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 static ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 /// A type that represents a request's ID.
@@ -809,29 +1470,267 @@ impl<'r> FromRequest<'r> for &'r RequestId {
         )
     }
 }
-pub struct Rewriter<'a> {
-    builder: &'a mut ast::ProgramBuilder<'a>,
-    theory: Rc<RefCell<DLTheory>>,
+/// A parsed statement waiting for the next grounding
+pub struct Statement(*mut clingo_ast_t);
+impl Clone for Statement {
+    fn clone(&self) -> Self {
+        unsafe { clingo_ast_acquire(self.0) };
+        Statement(self.0)
+    }
 }
-impl<'a> clingo::ast::StatementHandler for Rewriter<'a> {
-    fn on_statement(&mut self, stm: &ast::Statement) -> bool {
-        self.theory
-            .borrow_mut()
-            .rewrite_statement(stm, &mut self.builder)
+impl Drop for Statement {
+    fn drop(&mut self) {
+        unsafe { clingo_ast_release(self.0) };
     }
 }
-pub struct ConRewriter<'a> {
-    builder: &'a mut ast::ProgramBuilder<'a>,
-    theory: Rc<RefCell<ConTheory>>,
+/// Collects the statements of a program after every registered theory rewrote them
+struct Rewriter {
+    statements: Vec<Statement>,
+    theories: Vec<RegisteredTheory>,
 }
-impl<'a> clingo::ast::StatementHandler for ConRewriter<'a> {
-    fn on_statement(&mut self, stm: &ast::Statement) -> bool {
-        self.theory
+/// Pass a statement through the rewrites of the theories, the first theory rewrites it first
+fn rewrite_statement(
+    theories: &[RegisteredTheory],
+    ast: *mut clingo_ast_t,
+    add: &mut dyn FnMut(*mut clingo_ast_t) -> bool,
+) -> bool {
+    match theories.split_first() {
+        Some((registered, rest)) => registered
+            .theory
             .borrow_mut()
-            .rewrite_statement(stm, &mut self.builder)
+            .rewrite_ast(ast, &mut |ast| rewrite_statement(rest, ast, add)),
+        None => add(ast),
+    }
+}
+unsafe extern "C" fn on_statement(ast: *mut clingo_ast_t, data: *mut c_void) -> bool {
+    let rewriter = &mut *(data as *mut Rewriter);
+    let statements = &mut rewriter.statements;
+    rewrite_statement(&rewriter.theories, ast, &mut |ast| {
+        clingo_ast_acquire(ast);
+        statements.push(Statement(ast));
+        true
+    })
+}
+/// Parse a program with `parse` and rewrite its statements
+fn rewrite_program(
+    theories: &[RegisteredTheory],
+    context: &str,
+    parse: impl FnOnce(AstCallback, *mut c_void) -> bool,
+) -> Result<Vec<Statement>, ServerError> {
+    let mut rewriter = Rewriter {
+        statements: vec![],
+        theories: theories.to_vec(),
+    };
+    if parse(on_statement, &mut rewriter as *mut Rewriter as *mut c_void) {
+        Ok(rewriter.statements)
+    } else {
+        Err(clingo_error(context))
+    }
+}
+/// The name of a `#const` definition, `None` for other statements
+unsafe fn definition_name(ast: *mut clingo_ast_t) -> Result<Option<String>, ServerError> {
+    let mut ast_type = 0;
+    if !clingo_ast_get_type(ast, &mut ast_type) {
+        return Err(clingo_error("Solver::ground failed!"));
+    }
+    if ast_type != clingo_ast_type_e_clingo_ast_type_definition {
+        return Ok(None);
+    }
+    let mut name = ptr::null();
+    if !clingo_ast_attribute_get_string(
+        ast,
+        clingo_ast_attribute_e_clingo_ast_attribute_name,
+        &mut name,
+    ) {
+        return Err(clingo_error("Solver::ground failed!"));
+    }
+    Ok(Some(CStr::from_ptr(name).to_string_lossy().into_owned()))
+}
+/// Apply the constants like `-c name=value` to the statements
+///
+/// Each constant becomes a definition `#const name=value. [override]` replacing the definitions
+/// of the constant in the statements, it is added if the statements do not define the constant.
+fn define_constants(
+    statements: &mut Vec<Statement>,
+    constants: &[(String, Symbol)],
+) -> Result<(), ServerError> {
+    for (name, value) in constants {
+        if !is_predicate_name(name) {
+            return Err(ServerError::InternalError(format!(
+                "Solver::ground failed! Invalid constant name {}.",
+                name
+            )));
+        }
+        let text =
+            CString::new(format!("#const {}={}. [override]", name, value)).map_err(|_| {
+                ServerError::InternalError(format!(
+                    "Solver::ground failed! Invalid value {} for constant {}.",
+                    value, name
+                ))
+            })?;
+        let parsed = rewrite_program(&[], "Solver::ground failed!", |callback, data| unsafe {
+            clingo_ast_parse_string(
+                text.as_ptr(),
+                Some(callback),
+                data,
+                ptr::null_mut(),
+                None,
+                ptr::null_mut(),
+                20,
+            )
+        })?;
+        // the parsed program starts with `#program base.`
+        let mut definition = None;
+        for statement in parsed {
+            if unsafe { definition_name(statement.0)? }.is_some() {
+                definition = Some(statement);
+            }
+        }
+        let definition = definition.ok_or_else(|| {
+            ServerError::InternalError(format!(
+                "Solver::ground failed! Invalid value {} for constant {}.",
+                value, name
+            ))
+        })?;
+        let mut replaced = false;
+        for statement in statements.iter_mut() {
+            if unsafe { definition_name(statement.0)? }.as_deref() == Some(name.as_str()) {
+                *statement = definition.clone();
+                replaced = true;
+            }
+        }
+        if !replaced {
+            statements.push(definition);
+        }
+    }
+    Ok(())
+}
+/// Add the statements to the control object, the constants override their definitions
+fn add_statements(
+    ctl: &mut Control,
+    mut statements: Vec<Statement>,
+    constants: &[(String, Symbol)],
+) -> Result<(), ServerError> {
+    define_constants(&mut statements, constants)?;
+    if statements.is_empty() {
+        return Ok(());
+    }
+    let mut builder = ptr::null_mut();
+    if !unsafe {
+        clingo_program_builder_init(control_ptr(ctl), &mut builder)
+            && clingo_program_builder_begin(builder)
+    } {
+        return Err(clingo_error("Solver::ground failed!"));
+    }
+    let added = statements
+        .iter()
+        .all(|statement| unsafe { clingo_program_builder_add(builder, statement.0) });
+    // the builder is ended in any case, the first error is reported
+    let ended = unsafe { clingo_program_builder_end(builder) };
+    if added && ended {
+        Ok(())
+    } else {
+        Err(clingo_error("Solver::ground failed!"))
+    }
+}
+
+/// parse the given entries of a user statistics map, missing entries are left out
+fn parse_user_statistics(
+    stats: &Statistics,
+    root_key: u64,
+    name: &str,
+    keys: &[String],
+) -> Result<StatisticsResult, ClingoError> {
+    let mut array = vec![];
+    if stats.map_has_subkey(root_key, name)? {
+        let user_key = stats.map_at(root_key, name)?;
+        for key in keys {
+            if stats.map_has_subkey(user_key, key)? {
+                let subkey = stats.map_at(user_key, key)?;
+                array.push((key.clone(), parse_statistics(stats, subkey)?));
+            }
+        }
+    }
+    Ok(StatisticsResult::Map(array))
+}
+
+/// write the user statistics to the root map of the statistics, values are added if `accumulate` is set
+fn write_user_statistics(
+    stats: &mut Statistics,
+    entry: &StatisticsResult,
+    accumulate: bool,
+) -> Result<(), ClingoError> {
+    let root_key = stats.root()?;
+    write_statistics(stats, root_key, entry, accumulate)
+}
+/// recursively write an entry to the statistics object
+fn write_statistics(
+    stats: &mut Statistics,
+    key: u64,
+    entry: &StatisticsResult,
+    accumulate: bool,
+) -> Result<(), ClingoError> {
+    match entry {
+        StatisticsResult::Value(value) => {
+            let value = if accumulate {
+                stats.value_get(key)? + value
+            } else {
+                *value
+            };
+            stats.value_set(key, value)
+        }
+        StatisticsResult::Array(array) => {
+            let size = stats.array_size(key)?;
+            for (i, elem) in array.iter().enumerate() {
+                let subkey = if i < size {
+                    stats.array_at(key, i)?
+                } else {
+                    stats.array_push(key, elem.statistics_type())?
+                };
+                write_statistics(stats, subkey, elem, accumulate)?;
+            }
+            Ok(())
+        }
+        StatisticsResult::Map(array) => {
+            for (name, elem) in array {
+                let subkey = if stats.map_has_subkey(key, name)? {
+                    stats.map_at(key, name)?
+                } else {
+                    stats.map_add_subkey(key, name, elem.statistics_type())?
+                };
+                write_statistics(stats, subkey, elem, accumulate)?;
+            }
+            Ok(())
+        }
+        StatisticsResult::Empty => Ok(()),
     }
 }
 
+/// find the key of the entry at a dotted path
+fn statistics_key(
+    stats: &Statistics,
+    root_key: u64,
+    path: &str,
+) -> Result<Option<u64>, ClingoError> {
+    let mut key = root_key;
+    for segment in path.split('.') {
+        key = match stats.statistics_type(key)? {
+            StatisticsType::Map => {
+                if !stats.map_has_subkey(key, segment)? {
+                    return Ok(None);
+                }
+                stats.map_at(key, segment)?
+            }
+            StatisticsType::Array => match segment.parse() {
+                Ok(i) if i < stats.array_size(key)? => stats.array_at(key, i)?,
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+    }
+    Ok(Some(key))
+}
+
 /// recursively parse the statistics object
 fn parse_statistics(stats: &Statistics, key: u64) -> Result<StatisticsResult, ClingoError> {
     // get the type of an entry and switch over its various values
@@ -877,6 +1776,75 @@ fn parse_statistics(stats: &Statistics, key: u64) -> Result<StatisticsResult, Cl
     }
 }
 
+/// recursively parse a theory term
+fn parse_theory_term(atoms: &TheoryAtoms, term: Id) -> Result<TheoryTermResult, ClingoError> {
+    let parse_arguments = || -> Result<Vec<TheoryTermResult>, ClingoError> {
+        let arguments = atoms.term_arguments(term)?;
+        let mut array = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            array.push(parse_theory_term(atoms, *argument)?);
+        }
+        Ok(array)
+    };
+    match atoms.term_type(term)? {
+        TermType::Number => Ok(TheoryTermResult::Number {
+            number: atoms.term_number(term)?,
+        }),
+        TermType::Symbol => Ok(TheoryTermResult::Symbol {
+            name: atoms.term_name(term)?.to_string(),
+        }),
+        TermType::Function => Ok(TheoryTermResult::Function {
+            name: atoms.term_name(term)?.to_string(),
+            arguments: parse_arguments()?,
+        }),
+        TermType::Tuple => Ok(TheoryTermResult::Tuple {
+            arguments: parse_arguments()?,
+        }),
+        TermType::List => Ok(TheoryTermResult::List {
+            arguments: parse_arguments()?,
+        }),
+        TermType::Set => Ok(TheoryTermResult::Set {
+            arguments: parse_arguments()?,
+        }),
+    }
+}
+
+fn parse_theory_atom(atoms: &TheoryAtoms, atom: Id) -> Result<TheoryAtomResult, ClingoError> {
+    let mut elements = vec![];
+    for element in atoms.atom_elements(atom)? {
+        let mut tuple = vec![];
+        for term in atoms.element_tuple(*element)? {
+            tuple.push(parse_theory_term(atoms, *term)?);
+        }
+        let condition = atoms
+            .element_condition(*element)?
+            .iter()
+            .map(|lit| lit.get_integer())
+            .collect();
+        elements.push(TheoryElementResult {
+            tuple,
+            condition,
+            condition_id: atoms.element_condition_id(*element)?.get_integer(),
+        });
+    }
+    let guard = if atoms.atom_has_guard(atom)? {
+        let (operator, term) = atoms.atom_guard(atom)?;
+        Some(TheoryGuardResult {
+            operator: operator.to_string(),
+            term: parse_theory_term(atoms, term)?,
+        })
+    } else {
+        None
+    };
+    Ok(TheoryAtomResult {
+        term: parse_theory_term(atoms, atoms.atom_term(atom)?)?,
+        elements,
+        guard,
+        literal: atoms.atom_literal(atom)?.get_integer(),
+        text: atoms.atom_to_string(atom)?,
+    })
+}
+
 /// recursively parse the configuration object
 fn parse_configuration(conf: &Configuration, key: Id) -> Result<ConfigurationResult, ClingoError> {
     // get the type of an entry and switch over its various values
@@ -916,23 +1884,120 @@ fn __set_conf(
     conf: &mut Configuration,
     new_conf: &ConfigurationResult,
     key: Id,
-) -> Result<(), ClingoError> {
+    path: &str,
+) -> Result<(), ServerError> {
+    let unknown_key = |path: &str| {
+        ServerError::InternalError(format!(
+            "Solver::set_configuration failed! Unknown configuration key {}.",
+            path
+        ))
+    };
+    let subpath = |segment: &str| {
+        if path.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{}.{}", path, segment)
+        }
+    };
     match new_conf {
         ConfigurationResult::Value(v) => {
-            conf.value_set(key, v)?;
+            conf.value_set(key, v).map_err(|e| {
+                ServerError::InternalError(format!(
+                    "Solver::set_configuration failed! Invalid value {} for configuration key {}. {}",
+                    v, path, e
+                ))
+            })?;
         }
         ConfigurationResult::Array(arr) => {
+            let size = conf.array_size(key).map_err(|_| unknown_key(path))?;
             for (i, e) in arr.iter().enumerate() {
+                let path = subpath(&i.to_string());
+                if i >= size {
+                    return Err(unknown_key(&path));
+                }
                 let subkey = conf.array_at(key, i)?;
-                __set_conf(conf, e, subkey)?;
+                __set_conf(conf, e, subkey, &path)?;
             }
         }
         ConfigurationResult::Map(m) => {
             for (name, c) in m {
+                let path = subpath(name);
+                if !conf.map_has_subkey(key, name).unwrap_or(false) {
+                    return Err(unknown_key(&path));
+                }
                 let subkey = conf.map_at(key, name)?;
-                __set_conf(conf, c, subkey)?;
+                __set_conf(conf, c, subkey, &path)?;
             }
         }
     };
     Ok(())
 }
+/// find the key of the configuration entry at a dotted path
+fn configuration_key(
+    conf: &Configuration,
+    root_key: Id,
+    path: &str,
+) -> Result<Option<Id>, ClingoError> {
+    let mut key = root_key;
+    for segment in path.split('.') {
+        let configuration_type = conf.configuration_type(key)?;
+        key = match segment.parse::<usize>() {
+            Ok(i) if configuration_type.contains(ConfigurationType::ARRAY) => {
+                if i >= conf.array_size(key)? {
+                    return Ok(None);
+                }
+                conf.array_at(key, i)?
+            }
+            _ if configuration_type.contains(ConfigurationType::MAP) => {
+                if !conf.map_has_subkey(key, segment)? {
+                    return Ok(None);
+                }
+                conf.map_at(key, segment)?
+            }
+            _ => return Ok(None),
+        };
+    }
+    Ok(Some(key))
+}
+/// recursively describe the configuration object
+fn describe_configuration(
+    conf: &Configuration,
+    key: Id,
+) -> Result<ConfigurationDescription, ClingoError> {
+    let configuration_type = conf.configuration_type(key)?;
+    let mut types = vec![];
+    let mut description = ConfigurationDescription {
+        configuration_type: String::new(),
+        description: conf.description(key)?.to_string(),
+        value: None,
+        elements: None,
+        entries: None,
+    };
+    if configuration_type.contains(ConfigurationType::VALUE) {
+        types.push("value");
+        description.value = Some(conf.value_get(key)?);
+    }
+    if configuration_type.contains(ConfigurationType::ARRAY) {
+        types.push("array");
+        let size = conf.array_size(key)?;
+        let mut elements = Vec::with_capacity(size);
+        for i in 0..size {
+            let subkey = conf.array_at(key, i)?;
+            elements.push(describe_configuration(conf, subkey)?);
+        }
+        description.elements = Some(elements);
+    }
+    if configuration_type.contains(ConfigurationType::MAP) {
+        types.push("map");
+        let size = conf.map_size(key)?;
+        let mut entries = BTreeMap::new();
+        for i in 0..size {
+            let name = conf.map_subkey_name(key, i)?;
+            let subkey = conf.map_at(key, name)?;
+            entries.insert(name.to_string(), describe_configuration(conf, subkey)?);
+        }
+        description.entries = Some(entries);
+    }
+    description.configuration_type = types.join(",");
+    Ok(description)
+}